@@ -1,7 +1,7 @@
 use {
     crate::Shell,
     clap::{App, AppSettings, Arg},
-    std::str,
+    std::{convert::TryFrom, str},
 };
 
 #[derive(Clone)]
@@ -10,18 +10,13 @@ where
     'a: 'b,
 {
     pub app: App<'a, 'b>,
-    pub help_msg: String,
-    pub version_msg: String,
 }
 
 impl<'a, 'b> AppWrapper<'a, 'b>
 where
     'a: 'b,
 {
-    pub fn new(
-        app: App<'a, 'b>,
-        modify_app: impl FnOnce(App<'a, 'b>) -> App<'a, 'b>,
-    ) -> anyhow::Result<Self> {
+    pub fn new(app: App<'a, 'b>, modify_app: impl FnOnce(App<'a, 'b>) -> App<'a, 'b>) -> Self {
         let app = app
             .settings(&[
                 AppSettings::DisableHelpFlags,
@@ -40,27 +35,32 @@ where
                     .long("version")
                     .help("Prints version information"),
             );
-        let app = modify_app(app);
+        Self {
+            app: modify_app(app),
+        }
+    }
 
+    // Rendered only when needed, it's slow for apps with many arguments.
+    pub fn help_msg(&self) -> anyhow::Result<String> {
         let mut help_msg = Vec::new();
-        app.write_help(&mut help_msg)?;
-        let help_msg = str::from_utf8(&help_msg)?;
-        let mut version_msg = Vec::new();
-        app.write_long_version(&mut version_msg)?;
-        let version_msg = str::from_utf8(&version_msg)?;
+        self.app.write_help(&mut help_msg)?;
+        Ok(str::from_utf8(&help_msg)?.into())
+    }
 
-        Ok(Self {
-            app,
-            help_msg: help_msg.into(),
-            version_msg: version_msg.into(),
-        })
+    pub fn version_msg(&self) -> anyhow::Result<String> {
+        let mut version_msg = Vec::new();
+        self.app.write_long_version(&mut version_msg)?;
+        Ok(str::from_utf8(&version_msg)?.into())
     }
 
     // FIXME: Fix ZSH not generating the code for completion.
     pub fn completions_script(&mut self, bin_name: &str, shell: &Shell) -> anyhow::Result<String> {
         let mut completions_script = Vec::new();
-        self.app
-            .gen_completions_to(bin_name, shell.into(), &mut completions_script);
+        self.app.gen_completions_to(
+            bin_name,
+            clap::Shell::try_from(shell)?,
+            &mut completions_script,
+        );
         Ok(str::from_utf8(&completions_script)?.trim_end().into())
     }
 }
@@ -0,0 +1,1190 @@
+use {
+    crate::{
+        extensions::{Extensions, ValueType},
+        ident_type::IdentType,
+        var_names::{Suffix, VarNames},
+        Error, Shell,
+    },
+    anyhow::anyhow,
+    clap::{App, AppSettings, ArgSettings},
+    std::str,
+};
+
+// The parser of the scripts generated by `slap compile bash`, it reads the tables emitted before
+// it. It runs with bash 3.2 and doesn't break scripts using `set -eu`. Variables and functions
+// start with `__slap_` and are unset once the arguments are parsed. The errors are the ones of
+// clap, usage and suggestions included.
+const BASH_PARSER: &str = r#"# Prints the usage of the errors of the current app. Like clap, it's the usage of the app, unless
+# arguments that are listed when used were found: then it's the required arguments and those.
+__slap_error_usage() {
+    local k listed= usage=
+    for k in ${__slap_app_usage_order[__slap_app]}; do
+        if [[ -n ${__slap_occurs[k]-} && ${__slap_listed[k]} == 1 ]]; then
+            listed=1
+            usage="$usage ${__slap_name[k]}"
+        elif [[ ${__slap_required[k]} == 1 ]]; then
+            usage="$usage ${__slap_name[k]}"
+        fi
+    done
+    if [[ -n $listed ]]; then
+        printf 'USAGE:\n    %s%s' "${__slap_app_bin[__slap_app]}" "$usage"
+    else
+        printf '%s' "${__slap_app_usage[__slap_app]}"
+    fi
+}
+
+__slap_error() {
+    printf 'error: %s\n\n%s\n\nFor more information try --help\n' "$1" "$(__slap_error_usage)" >&2
+    exit 1
+}
+
+# Errors printed without the usage, like the ones of clap validators.
+__slap_fail() {
+    printf 'error: %s\n' "$1" >&2
+    exit 1
+}
+
+# Prints $1 in green, like clap does in the suggestions of some errors even without a terminal.
+__slap_good() {
+    printf '\033[32m%s\033[0m' "$1"
+}
+
+# $2 is the suggestion appended to the error, if any.
+__slap_unexpected() {
+    __slap_error "Found argument '$1' which wasn't expected, or isn't valid in this context${2-}"
+}
+
+# Sets __slap_found to the argument of the current app whose column $1 is $2.
+__slap_find() {
+    local k value
+    for k in ${__slap_app_args[__slap_app]}; do
+        eval "value=\${$1[k]}"
+        if [[ -n $2 && $value == "$2" ]]; then
+            __slap_found=$k
+            return 0
+        fi
+    done
+    return 1
+}
+
+# Sets __slap_found to the app of the subcommand $1 of the app $2.
+__slap_find_subcommand() {
+    local names=(${__slap_app_subcommands[$2]}) apps=(${__slap_app_subapps[$2]}) i=0
+    while [[ $i -lt ${#names[@]} ]]; do
+        if [[ ${names[i]} == "$1" ]]; then
+            __slap_found=${apps[i]}
+            return 0
+        fi
+        i=$((i + 1))
+    done
+    return 1
+}
+
+__slap_start() {
+    if [[ -z ${__slap_occurs[$1]-} ]]; then
+        __slap_occurs[$1]=0
+        __slap_nvals[$1]=0
+        __slap_present="$__slap_present $1"
+        eval "${__slap_indices_var[$1]}=(); ${__slap_vals_var[$1]}=()"
+    fi
+}
+
+__slap_occur() {
+    __slap_start "$1"
+    __slap_occurs[$1]=$((__slap_occurs[$1] + 1))
+}
+
+__slap_value() {
+    __slap_idx=$((__slap_idx + 1))
+    __slap_nvals[$1]=$((__slap_nvals[$1] + 1))
+    eval "${__slap_indices_var[$1]}+=(\"\$__slap_idx\"); ${__slap_vals_var[$1]}+=(\"\$2\")"
+}
+
+__slap_flag() {
+    case ${__slap_kind[$1]} in
+    help)
+        printf '%s\n' "${__slap_app_help[__slap_app]}"
+        exit 0
+        ;;
+    version)
+        printf '%s\n' "${__slap_app_version[__slap_app]}"
+        exit 0
+        ;;
+    esac
+    __slap_occur "$1"
+    eval "${__slap_indices_var[$1]}+=(\"\$__slap_idx\")"
+}
+
+# An option followed by $2 (0 or 1) values, $3 is the value and $4 is 1 if it was given with `=`.
+__slap_option() {
+    __slap_occur "$1"
+    __slap_pending=$1
+    if [[ $2 == 1 ]]; then
+        __slap_value "$1" "$3"
+        if [[ $4 == 1 || ${__slap_multiple[$1]} != 1 ]]; then
+            __slap_pending=
+        fi
+    fi
+}
+
+__slap_switch() {
+    if [[ ${__slap_kind[$1]} == opt ]]; then
+        __slap_option "$@"
+    else
+        __slap_flag "$1"
+    fi
+}
+
+__slap_long() {
+    local name=${1#--} value= has_value=0
+    if [[ $name == *=* ]]; then
+        value=${name#*=}
+        name=${name%%=*}
+        has_value=1
+        while [[ $value == =* ]]; do
+            value=${value#=}
+        done
+    fi
+    __slap_idx=$((__slap_idx + 1))
+    if ! __slap_find __slap_long "$name"; then
+        __slap_unknown_long "$name"
+    fi
+    __slap_switch "$__slap_found" "$has_value" "$value" 1
+}
+
+__slap_short() {
+    local chars=${1#-} i=0 value eq
+    while [[ $i -lt ${#chars} ]]; do
+        __slap_idx=$((__slap_idx + 1))
+        if ! __slap_find __slap_short "${chars:i:1}"; then
+            __slap_unexpected "-${chars:i:1}"
+        fi
+        i=$((i + 1))
+        if [[ ${__slap_kind[__slap_found]} == opt ]]; then
+            value=${chars:i}
+            if [[ -z $value ]]; then
+                __slap_option "$__slap_found" 0
+                return 0
+            fi
+            eq=0
+            while [[ $value == =* ]]; do
+                value=${value#=}
+                eq=1
+            done
+            __slap_option "$__slap_found" 1 "$value" "$eq"
+            return 0
+        fi
+        __slap_flag "$__slap_found"
+    done
+}
+
+# Sets __slap_found to the most similar of the names $2... to $1, if one is similar enough. The
+# similarity is the Jaro-Winkler one of strsim, computed with fractions.
+__slap_closest() {
+    local a=$1 al=${#1} name best= best_num=0 best_den=1
+    local bl range i j lo hi m t last p num den consumed
+    shift
+    for name in "$@"; do
+        bl=${#name}
+        if [[ $a == "$name" ]]; then
+            num=1 den=1
+        elif [[ $al == 0 || $bl == 0 || $al == 1 && $bl == 1 ]]; then
+            continue
+        else
+            range=$(((al > bl ? al : bl) / 2 - 1))
+            consumed=() m=0 t=0 last=0 i=0
+            while [[ $i -lt $al ]]; do
+                lo=$((i > range ? i - range : 0))
+                hi=$((i + range < bl - 1 ? i + range : bl - 1))
+                j=$lo
+                while [[ $j -le $hi ]]; do
+                    if [[ ${name:j:1} == "${a:i:1}" && -z ${consumed[j]-} ]]; then
+                        consumed[j]=1
+                        m=$((m + 1))
+                        if [[ $j -lt $last ]]; then
+                            t=$((t + 1))
+                        fi
+                        last=$j
+                        break
+                    fi
+                    j=$((j + 1))
+                done
+                i=$((i + 1))
+            done
+            if [[ $m == 0 ]]; then
+                continue
+            fi
+            p=0
+            while [[ $p -lt $al && $p -lt $bl && ${a:p:1} == "${name:p:1}" ]]; do
+                p=$((p + 1))
+            done
+            # jaro = num / den, then jaro_winkler = jaro + p / 10 * (1 - jaro), at most 1.
+            num=$((m * m * bl + m * m * al + (m - t) * al * bl))
+            den=$((3 * al * bl * m))
+            num=$((10 * num + p * (den - num)))
+            den=$((10 * den))
+            num=$((num > den ? den : num))
+        fi
+        if [[ $((10 * num)) -gt $((8 * den)) && $((num * best_den)) -gt $((best_num * den)) ]]; then
+            best=$name best_num=$num best_den=$den
+        fi
+    done
+    __slap_found=$best
+    [[ -n $best ]]
+}
+
+# Like clap, rejects the values that look like the name of a subcommand, even after `--`.
+__slap_similar() {
+    local names=(${__slap_app_subcommands[__slap_app]})
+    if [[ ${__slap_app_help_subcommand[__slap_app]} == 1 ]]; then
+        names+=(help)
+    fi
+    if __slap_closest "$1" ${names[@]+"${names[@]}"}; then
+        __slap_error "The subcommand '$1' wasn't recognized
+	Did you mean '$__slap_found'?
+
+If you believe you received this message in error, try re-running with '${__slap_app_bin[__slap_app]} -- $1'"
+    fi
+}
+
+# The error of the unknown long argument $1. Like clap, it suggests a similar long argument of
+# the app, or else of the subcommand given the soonest in the rest of the arguments.
+__slap_unknown_long() {
+    local longs=() names subapps k i=0 rest=() score best=
+    for k in ${__slap_app_longs[__slap_app]}; do
+        longs+=("${__slap_long[k]}")
+    done
+    if __slap_closest "$1" ${longs[@]+"${longs[@]}"}; then
+        __slap_find __slap_long "$__slap_found"
+        # The suggested argument is used by the usage of the error.
+        __slap_start "$__slap_found"
+        __slap_unexpected "--$1" "
+	Did you mean $(__slap_good --)$(__slap_good "${__slap_long[__slap_found]}")?"
+    fi
+    names=(${__slap_app_subcommands[__slap_app]})
+    subapps=(${__slap_app_subapps[__slap_app]})
+    rest=(${__slap_args[@]+"${__slap_args[@]:__slap_next}"})
+    while [[ $i -lt ${#names[@]} ]]; do
+        longs=()
+        # Clap adds the help and version flags of nested subcommands when they are parsed.
+        for k in ${__slap_app_longs[subapps[i]]}; do
+            if [[ ${__slap_kind[k]} != help && ${__slap_kind[k]} != version ]]; then
+                longs+=("${__slap_long[k]}")
+            fi
+        done
+        if __slap_closest "$1" ${longs[@]+"${longs[@]}"}; then
+            score=0
+            while [[ $score -lt ${#rest[@]} && ${rest[score]} != "${names[i]}" ]]; do
+                score=$((score + 1))
+            done
+            if [[ $score -lt ${#rest[@]} && (-z $best || $score -lt ${best%% *}) ]]; then
+                best="$score ${names[i]} $__slap_found"
+            fi
+        fi
+        i=$((i + 1))
+    done
+    if [[ -n $best ]]; then
+        best=${best#* }
+        __slap_unexpected "--$1" "
+	Did you mean to put '$(__slap_good --)$(__slap_good "${best#* }")' after the subcommand '$(__slap_good "${best%% *}")'?"
+    fi
+    __slap_unexpected "--$1"
+}
+
+__slap_positional() {
+    local positionals=(${__slap_app_positionals[__slap_app]}) k
+    __slap_similar "$1"
+    if [[ $__slap_pos -ge ${#positionals[@]} ]]; then
+        if [[ -z ${__slap_app_args[__slap_app]} ]]; then
+            printf "error: The subcommand '%s' wasn't recognized\n\nUSAGE:\n\t%s help <subcommands>...\n\nFor more information try --help\n" \
+                "$1" "${__slap_app_bin[__slap_app]}" >&2
+            exit 1
+        fi
+        __slap_unexpected "$1"
+    fi
+    k=${positionals[__slap_pos]}
+    __slap_occur "$k"
+    __slap_value "$k" "$1"
+    if [[ ${__slap_multiple[k]} != 1 ]]; then
+        __slap_pos=$((__slap_pos + 1))
+    fi
+}
+
+# The help subcommand of apps nested in subcommands, added by clap, that prints the help of the
+# subcommands given as arguments.
+__slap_help_subcommand() {
+    local app=$__slap_app
+    while [[ $# -gt 0 ]]; do
+        if ! __slap_find_subcommand "$1" "$app"; then
+            __slap_error "The subcommand '$1' wasn't recognized"
+        fi
+        app=$__slap_found
+        shift
+    done
+    printf '%s\n' "${__slap_app_help[app]}"
+    exit 0
+}
+
+__slap_int() {
+    local value=$1 sign= max=9223372036854775807
+    case $value in
+    [+-]*)
+        sign=${value:0:1}
+        value=${value:1}
+        ;;
+    esac
+    case $value in
+    '' | *[!0-9]*) return 1 ;;
+    esac
+    while [[ $value == 0?* ]]; do
+        value=${value#0}
+    done
+    if [[ $sign == - ]]; then
+        max=9223372036854775808
+    fi
+    if [[ ${#value} -gt ${#max} || (${#value} -eq ${#max} && $value > $max) ]]; then
+        return 1
+    fi
+    if [[ $sign == - && $value != 0 ]]; then
+        value=-$value
+    fi
+    __slap_converted=$value
+}
+
+__slap_bool() {
+    case $1 in
+    [Tt][Rr][Uu][Ee] | [Yy][Ee][Ss] | [Oo][Nn] | 1) __slap_converted=true ;;
+    [Ff][Aa][Ll][Ss][Ee] | [Nn][Oo] | [Oo][Ff][Ff] | 0) __slap_converted=false ;;
+    *) return 1 ;;
+    esac
+}
+
+# Sets __slap_vals to the values of the argument $1.
+__slap_get_vals() {
+    __slap_vals=()
+    if [[ ${__slap_nvals[$1]} -gt 0 ]]; then
+        eval "__slap_vals=(\"\${${__slap_vals_var[$1]}[@]}\")"
+    fi
+}
+
+__slap_check_values() {
+    local value possible rest suffix
+    __slap_get_vals "$1"
+    for value in ${__slap_vals[@]+"${__slap_vals[@]}"}; do
+        if [[ -n ${__slap_possible[$1]} ]]; then
+            if [[ $value == *$'\n'* || ${__slap_possible[$1]} != *"
+$value
+"* ]]; then
+                possible=() rest=${__slap_possible[$1]#?} suffix=
+                while [[ -n $rest ]]; do
+                    possible+=("${rest%%$'\n'*}")
+                    rest=${rest#*$'\n'}
+                done
+                if __slap_closest "$value" "${possible[@]}"; then
+                    suffix="
+	Did you mean '$(__slap_good "$__slap_found")'?"
+                fi
+                __slap_error "'$value' isn't a valid value for '${__slap_name[$1]}'
+	[possible values: ${__slap_possible_list[$1]}]
+$suffix"
+            fi
+        fi
+        case ${__slap_type[$1]} in
+        int)
+            if ! __slap_int "$value"; then
+                __slap_fail "Invalid value for '${__slap_name[$1]}': must be an integer"
+            fi
+            ;;
+        bool)
+            if ! __slap_bool "$value"; then
+                __slap_fail "Invalid value for '${__slap_name[$1]}': must be a boolean (true, false, yes, no, on, off, 1, 0)"
+            fi
+            ;;
+        esac
+    done
+}
+
+# Checks the arguments of the app $1, $2 is 1 for the last app of the command line.
+__slap_validate() {
+    local k missing=
+    __slap_app=$1
+    for k in ${__slap_app_usage_order[$1]}; do
+        if [[ ${__slap_required[k]} == 1 && -z ${__slap_occurs[k]-} ]]; then
+            missing="$missing
+    ${__slap_name[k]}"
+        fi
+    done
+    if [[ -n $missing ]]; then
+        __slap_error "The following required arguments were not provided:$missing"
+    fi
+    if [[ $2 == 1 && -n $__slap_pending && ${__slap_nvals[__slap_pending]} == 0 ]]; then
+        __slap_error "The argument '${__slap_name[__slap_pending]}' requires a value but none was supplied"
+    fi
+    for k in ${__slap_app_args[$1]}; do
+        if [[ -n ${__slap_occurs[k]-} ]]; then
+            if [[ ${__slap_kind[k]} == opt && ${__slap_nvals[k]} == 0 ]]; then
+                __slap_error "The argument '${__slap_name[k]}' requires a value but none was supplied"
+            fi
+            if [[ ${__slap_multiple[k]} != 1 && ${__slap_occurs[k]} -gt 1 ]]; then
+                __slap_error "The argument '${__slap_name[k]}' was provided more than once, but cannot be used multiple times"
+            fi
+            __slap_check_values "$k"
+        fi
+    done
+}
+
+# The help and version flags added by slap are handled once the arguments are valid, for the
+# first subcommand or for the app if there are no subcommands.
+__slap_messages() {
+    local k=${__slap_app_help_arg[$1]}
+    if [[ -n $k && -n ${__slap_occurs[k]-} ]]; then
+        printf '%s\n' "${__slap_app_help[$1]}"
+        exit 0
+    fi
+    k=${__slap_app_version_arg[$1]}
+    if [[ -n $k && -n ${__slap_occurs[k]-} ]]; then
+        printf '%s\n' "${__slap_app_version[$1]}"
+        exit 0
+    fi
+    # The help subcommand added by slap prints the help of the app or of one of its subcommands.
+    if [[ ${__slap_app_role[$1]} == help ]]; then
+        k=${__slap_app_positionals[$1]}
+        if [[ -n ${__slap_occurs[k]-} ]]; then
+            __slap_get_vals "$k"
+            if [[ ${__slap_vals[0]} == help ]] || ! __slap_find_subcommand "${__slap_vals[0]}" 0; then
+                __slap_fail "The subcommand '${__slap_vals[0]}' wasn't recognized"
+            fi
+            printf '%s\n' "${__slap_app_help[__slap_found]}"
+        else
+            printf '%s\n' "${__slap_app_help[0]}"
+        fi
+        exit 0
+    fi
+}
+
+__slap_finish() {
+    local k=$1 i=0
+    __slap_get_vals "$k"
+    case ${__slap_type[k]} in
+    int | bool)
+        while [[ $i -lt ${__slap_nvals[k]} ]]; do
+            "__slap_${__slap_type[k]}" "${__slap_vals[i]}"
+            __slap_vals[i]=$__slap_converted
+            i=$((i + 1))
+        done
+        # Without the integer attribute, like `slap parse`.
+        eval "${__slap_vals_var[k]}=(\"\${__slap_vals[@]}\")"
+        ;;
+    esac
+    eval "${__slap_occurs_var[k]}=\${__slap_occurs[k]}; ${__slap_count_var[k]}=\${__slap_nvals[k]}"
+}
+
+__slap_parse() {
+    local IFS=' ' arg apps i k last=1
+    __slap_app=0 __slap_apps=0 __slap_idx=0 __slap_pos=0 __slap_pending= __slap_trailing=
+    __slap_present= __slap_occurs=() __slap_nvals=()
+    # The arguments, for the suggestions that look at the ones after $__slap_next.
+    __slap_args=(${1+"$@"}) __slap_next=0
+    while [[ $# -gt 0 ]]; do
+        arg=$1
+        shift
+        __slap_next=$((__slap_next + 1))
+        if [[ -n $__slap_pending ]]; then
+            if [[ ${__slap_hyphen[__slap_pending]} == 1 || $arg != -?* ]]; then
+                __slap_value "$__slap_pending" "$arg"
+                if [[ ${__slap_multiple[__slap_pending]} != 1 ]]; then
+                    __slap_pending=
+                fi
+                continue
+            fi
+            __slap_pending=
+        fi
+        if [[ -n $__slap_trailing ]]; then
+            __slap_positional "$arg"
+        elif [[ $arg == -- ]]; then
+            __slap_trailing=1
+        elif [[ $arg == --* ]]; then
+            __slap_long "$arg"
+        elif [[ $arg == -?* ]]; then
+            __slap_short "$arg"
+        elif __slap_find_subcommand "$arg" "$__slap_app"; then
+            eval "${__slap_app_subcommand_var[__slap_app]}=\$arg"
+            __slap_app=$__slap_found
+            __slap_apps="$__slap_apps $__slap_app"
+            __slap_idx=0
+            __slap_pos=0
+        elif [[ $arg == help && ${__slap_app_help_subcommand[__slap_app]} == 1 ]]; then
+            __slap_help_subcommand ${1+"$@"}
+        else
+            __slap_positional "$arg"
+        fi
+    done
+
+    # Like clap, the subcommands are checked before the apps that contain them.
+    apps=($__slap_apps)
+    i=${#apps[@]}
+    while [[ $i -gt 0 ]]; do
+        i=$((i - 1))
+        __slap_validate "${apps[i]}" "$last"
+        last=0
+    done
+    __slap_messages "${apps[${#apps[@]} > 1 ? 1 : 0]}"
+
+    eval "$__slap_success_var=true"
+    for k in $__slap_apps; do
+        eval "${__slap_app_usage_var[k]}=\${__slap_app_usage[k]}"
+    done
+    for k in $__slap_present; do
+        __slap_finish "$k"
+    done
+}
+
+__slap_parse ${1+"$@"}
+unset -f __slap_error_usage __slap_error __slap_fail __slap_good __slap_unexpected __slap_find \
+    __slap_find_subcommand __slap_start __slap_occur __slap_value __slap_flag __slap_option \
+    __slap_switch __slap_long __slap_short __slap_closest __slap_similar __slap_unknown_long \
+    __slap_positional __slap_help_subcommand __slap_int __slap_bool __slap_get_vals \
+    __slap_check_values __slap_validate __slap_messages __slap_finish __slap_parse
+unset __slap_kind __slap_short __slap_long __slap_multiple __slap_required __slap_hyphen \
+    __slap_listed __slap_possible __slap_possible_list __slap_type __slap_name __slap_occurs_var \
+    __slap_indices_var __slap_vals_var __slap_count_var __slap_app_args __slap_app_positionals \
+    __slap_app_usage_order __slap_app_longs __slap_app_subcommands __slap_app_subapps \
+    __slap_app_bin __slap_app_usage __slap_app_usage_var __slap_app_subcommand_var \
+    __slap_app_help __slap_app_version __slap_app_help_arg __slap_app_version_arg __slap_app_role \
+    __slap_app_help_subcommand __slap_success_var __slap_app __slap_apps __slap_idx __slap_pos \
+    __slap_pending __slap_trailing __slap_present __slap_occurs __slap_nvals __slap_found \
+    __slap_vals __slap_converted __slap_args __slap_next"#;
+
+// An argument of the tables read by `BASH_PARSER`.
+struct ArgRow {
+    // "flag", "opt", "pos", or "help" and "version" for the flags that clap adds to subcommands
+    // of subcommands, that print their message as soon as they are found.
+    kind: &'static str,
+    short: Option<char>,
+    long: Option<String>,
+    multiple: bool,
+    required: bool,
+    hyphen: bool,
+    // Whether the argument is in the usage of the errors once it's used, see `create_error_usage`
+    // of clap.
+    listed: bool,
+    possible: Vec<String>,
+    value_type: ValueType,
+    // The argument as clap writes it in error messages, like `--opt <opt>`.
+    display: String,
+    // The variables of the occurrences, indices, values and values count.
+    vars: [String; 4],
+}
+
+// An app of the tables read by `BASH_PARSER`, the root app or a subcommand.
+#[derive(Default)]
+struct AppRow {
+    args: Vec<usize>,
+    positionals: Vec<usize>,
+    // The arguments in the order of the usage of errors: positionals, then the others by name.
+    usage_order: Vec<usize>,
+    // The arguments with a long name, in the order clap suggests them.
+    longs: Vec<usize>,
+    subcommands: Vec<(String, usize)>,
+    bin_name: String,
+    usage: String,
+    usage_var: String,
+    subcommand_var: String,
+    help: String,
+    version: String,
+    // The help and version flags that slap adds to the root app and its subcommands.
+    help_arg: Option<usize>,
+    version_arg: Option<usize>,
+    // "help" for the help subcommand added by slap.
+    role: &'static str,
+    // Whether clap adds a help subcommand.
+    help_subcommand: bool,
+}
+
+// The help and version messages of the app at a subcommand path, rendered by
+// `ExternalApp::compile` that knows how slap renders them.
+pub(crate) type Messages<'m> = dyn FnMut(&[&str], &App) -> Result<(String, String), Error> + 'm;
+
+struct Compiler<'a, 'm> {
+    extensions: &'a Extensions,
+    var_names: VarNames,
+    prefix: &'a str,
+    bin_name: &'a str,
+    messages: &'a mut Messages<'m>,
+    args: Vec<ArgRow>,
+    apps: Vec<AppRow>,
+}
+
+// The key of the YAML config of an argument that `slap compile` can't express, if any.
+fn unsupported_arg_key(app: &App, name: &str) -> Option<&'static str> {
+    let base = app
+        .p
+        .flags
+        .iter()
+        .map(|x| &x.b)
+        .chain(app.p.opts.iter().map(|x| &x.b))
+        .chain(app.p.positionals.values().map(|x| &x.b))
+        .find(|x| x.name == name)?;
+    let switched = app
+        .p
+        .flags
+        .iter()
+        .filter(|x| x.b.name == name)
+        .map(|x| &x.s)
+        .chain(app.p.opts.iter().filter(|x| x.b.name == name).map(|x| &x.s))
+        .next();
+    let valued = app
+        .p
+        .opts
+        .iter()
+        .filter(|x| x.b.name == name)
+        .map(|x| &x.v)
+        .chain(
+            app.p
+                .positionals
+                .values()
+                .filter(|x| x.b.name == name)
+                .map(|x| &x.v),
+        )
+        .next();
+
+    let settings = [
+        (ArgSettings::Global, "global"),
+        (ArgSettings::RequireEquals, "require_equals"),
+        (ArgSettings::Last, "last"),
+        (ArgSettings::CaseInsensitive, "case_insensitive"),
+        (ArgSettings::RequireDelimiter, "require_delimiter"),
+    ];
+    if let Some((_, key)) = settings.iter().find(|(x, _)| base.is_set(*x)) {
+        return Some(key);
+    }
+    if base.blacklist.is_some() {
+        return Some("conflicts_with");
+    }
+    if base.requires.is_some() {
+        return Some("requires");
+    }
+    if base.r_unless.is_some() {
+        return Some("required_unless");
+    }
+    if base.overrides.is_some() {
+        return Some("overrides_with");
+    }
+    if base.groups.is_some() {
+        return Some("group");
+    }
+    if app.p.r_ifs.iter().any(|(x, _, _)| *x == name) {
+        return Some("required_if");
+    }
+    if switched.is_some_and(|x| x.aliases.is_some()) {
+        return Some("aliases");
+    }
+    let valued = valued?;
+    if !base.is_set(ArgSettings::EmptyValues) {
+        return Some("empty_values");
+    }
+    if valued.val_delim.is_some() {
+        return Some("use_delimiter");
+    }
+    if valued.num_vals.is_some() {
+        return Some("number_of_values");
+    }
+    if valued.min_vals.is_some() {
+        return Some("min_values");
+    }
+    if valued.max_vals.is_some() {
+        return Some("max_values");
+    }
+    if valued.default_val.is_some() {
+        return Some("default_value");
+    }
+    if valued.default_vals_ifs.is_some() {
+        return Some("default_value_if");
+    }
+    if valued.env.is_some() {
+        return Some("env");
+    }
+    if valued.terminator.is_some() {
+        return Some("value_terminator");
+    }
+    None
+}
+
+// The usage of an app, as clap puts it in the matches.
+fn usage(app: &App, bin_name: String) -> anyhow::Result<String> {
+    let mut app = app.clone().template("{usage}");
+    app.p.meta.bin_name = Some(bin_name);
+    app.p.create_help_and_version();
+    let mut usage = Vec::new();
+    app.write_help(&mut usage)?;
+    Ok(format!("USAGE:\n    {}", str::from_utf8(&usage)?))
+}
+
+impl<'a, 'm> Compiler<'a, 'm> {
+    fn var(&self, path: &[&str], arg: &str, suffix: Option<Suffix>) -> Result<String, Error> {
+        let name = self
+            .var_names
+            .name(self.prefix, path, arg, suffix)
+            .map_err(Error::Codegen)?;
+        Shell::Bash
+            .ident_check(&name, &IdentType::Head)
+            .map_err(Error::Codegen)?;
+        Ok(name)
+    }
+
+    fn arg(
+        &mut self,
+        path: &[&str],
+        name: &str,
+        kind: &'static str,
+        switched: (Option<char>, Option<&str>),
+        display: String,
+    ) -> Result<usize, Error> {
+        Shell::Bash
+            .ident_check(name, &IdentType::Tail)
+            .map_err(Error::Codegen)?;
+        let vars = [
+            self.var(path, name, Some(Suffix::Occurs))?,
+            self.var(path, name, Some(Suffix::Indices))?,
+            self.var(path, name, Some(Suffix::Vals))?,
+            self.var(path, name, Some(Suffix::ValsCount))?,
+        ];
+        let value_type = self
+            .extensions
+            .arg(path, name)
+            .and_then(|x| x.value_type)
+            .unwrap_or(ValueType::String);
+        self.args.push(ArgRow {
+            kind,
+            short: switched.0,
+            long: switched.1.map(String::from),
+            multiple: false,
+            required: false,
+            hyphen: false,
+            listed: false,
+            possible: Vec::new(),
+            value_type,
+            display,
+            vars,
+        });
+        Ok(self.args.len() - 1)
+    }
+
+    fn app<'b>(&mut self, app: &'b App, path: &mut Vec<&'b str>) -> Result<usize, Error> {
+        let id = self.apps.len();
+        self.apps.push(AppRow::default());
+        let describe = || {
+            if path.is_empty() {
+                "the app".to_string()
+            } else {
+                format!("subcommand '{}'", path.join(" "))
+            }
+        };
+        // The help subcommand and the help and version flags of the root app and of its
+        // subcommands are added by slap.
+        let role = match path.as_slice() {
+            ["help"] => "help",
+            [] | [_] => "",
+            _ => "native",
+        };
+
+        if role != "help" {
+            for x in path.iter() {
+                Shell::Bash
+                    .ident_check(x, &IdentType::Tail)
+                    .map_err(Error::Codegen)?;
+            }
+            let settings = [
+                AppSettings::AllowExternalSubcommands,
+                AppSettings::AllowLeadingHyphen,
+                AppSettings::AllowMissingPositional,
+                AppSettings::AllowNegativeNumbers,
+                AppSettings::AllArgsOverrideSelf,
+                AppSettings::ArgRequiredElseHelp,
+                AppSettings::ArgsNegateSubcommands,
+                AppSettings::DontDelimitTrailingValues,
+                AppSettings::GlobalVersion,
+                AppSettings::InferSubcommands,
+                AppSettings::SubcommandRequired,
+                AppSettings::SubcommandRequiredElseHelp,
+                AppSettings::SubcommandsNegateReqs,
+                AppSettings::TrailingVarArg,
+                AppSettings::VersionlessSubcommands,
+            ];
+            if let Some(x) = settings.iter().find(|x| app.p.is_set(**x)) {
+                return Err(Error::Config(anyhow!(
+                    "`slap compile` doesn't support the `{:?}` setting, used by {}",
+                    x,
+                    describe()
+                )));
+            }
+            if !app.p.groups.is_empty() {
+                return Err(Error::Config(anyhow!(
+                    "`slap compile` doesn't support `groups`, used by {}",
+                    describe()
+                )));
+            }
+            if app.p.meta.aliases.is_some() {
+                return Err(Error::Config(anyhow!(
+                    "`slap compile` doesn't support `aliases`, used by {}",
+                    describe()
+                )));
+            }
+        }
+
+        let mut row = AppRow {
+            role,
+            ..AppRow::default()
+        };
+        let (help, version) = (self.messages)(path, app)?;
+        row.help = help;
+        row.version = version;
+        let bin_name = std::iter::once(self.bin_name)
+            .chain(path.iter().copied())
+            .collect::<Vec<_>>()
+            .join(" ");
+        row.usage = usage(app, bin_name.clone()).map_err(Error::Codegen)?;
+        row.bin_name = bin_name;
+        row.usage_var = self.var(path, "usage", None)?;
+
+        let unsupported = |name: &str| match unsupported_arg_key(app, name) {
+            Some(key) if role != "help" => Err(Error::Config(anyhow!(
+                "`slap compile` doesn't support `{}`, used by argument '{}' of {}",
+                key,
+                name,
+                describe()
+            ))),
+            _ => Ok(()),
+        };
+        // The arguments that aren't positional, by name.
+        let mut named = Vec::new();
+        for x in app.p.flags.iter() {
+            unsupported(x.b.name)?;
+            let k = self.arg(path, x.b.name, "flag", (x.s.short, x.s.long), x.to_string())?;
+            self.args[k].multiple = x.b.is_set(ArgSettings::Multiple);
+            self.args[k].listed = true;
+            if role != "native" {
+                match x.b.name {
+                    "help" => row.help_arg = Some(k),
+                    "version" => row.version_arg = Some(k),
+                    _ => {}
+                }
+            }
+            row.args.push(k);
+            named.push((x.b.name, k));
+        }
+        let mut opt_longs = Vec::new();
+        for x in app.p.opts.iter() {
+            unsupported(x.b.name)?;
+            let k = self.arg(path, x.b.name, "opt", (x.s.short, x.s.long), x.to_string())?;
+            let row_arg = &mut self.args[k];
+            row_arg.multiple = x.b.is_set(ArgSettings::Multiple);
+            row_arg.required = x.b.is_set(ArgSettings::Required);
+            row_arg.hyphen = x.b.is_set(ArgSettings::AllowLeadingHyphen);
+            row_arg.listed = !row_arg.required && !x.b.is_set(ArgSettings::Hidden);
+            row_arg.possible =
+                x.v.possible_vals
+                    .iter()
+                    .flatten()
+                    .map(|x| x.to_string())
+                    .collect();
+            row.args.push(k);
+            named.push((x.b.name, k));
+            if x.s.long.is_some() {
+                opt_longs.push(k);
+            }
+        }
+        let positionals = app.p.positionals.values().collect::<Vec<_>>();
+        for (idx, x) in positionals.iter().enumerate() {
+            unsupported(x.b.name)?;
+            let multiple = x.b.is_set(ArgSettings::Multiple);
+            if multiple && idx + 1 != positionals.len() {
+                return Err(Error::Config(anyhow!(
+                    "`slap compile` supports `multiple` only on the last positional argument, not on argument '{}' of {}",
+                    x.b.name,
+                    describe()
+                )));
+            }
+            let k = self.arg(path, x.b.name, "pos", (None, None), x.to_string())?;
+            let row_arg = &mut self.args[k];
+            row_arg.multiple = multiple;
+            row_arg.required = x.b.is_set(ArgSettings::Required);
+            row_arg.listed = !row_arg.required && x.b.is_set(ArgSettings::Hidden);
+            row_arg.possible =
+                x.v.possible_vals
+                    .iter()
+                    .flatten()
+                    .map(|x| x.to_string())
+                    .collect();
+            row.args.push(k);
+            row.positionals.push(k);
+        }
+        row.longs = named
+            .iter()
+            .filter(|(_, k)| self.args[*k].kind == "flag" && self.args[*k].long.is_some())
+            .map(|(_, k)| *k)
+            .collect();
+
+        // Subcommands of subcommands get the help and version flags and the help subcommand
+        // from clap, when they are parsed.
+        if role == "native" {
+            let has_long = |long| {
+                app.p.flags.iter().any(|x| x.s.long == Some(long))
+                    || app.p.opts.iter().any(|x| x.s.long == Some(long))
+            };
+            let has_short = |short| {
+                app.p.flags.iter().any(|x| x.s.short == Some(short))
+                    || app.p.opts.iter().any(|x| x.s.short == Some(short))
+            };
+            if !app.p.is_set(AppSettings::DisableHelpFlags) && !has_long("help") {
+                let short = Some('h').filter(|x| !has_short(*x));
+                let k = self.arg(path, "help", "help", (short, Some("help")), "--help".into())?;
+                row.args.push(k);
+                row.longs.push(k);
+                named.push(("help", k));
+            }
+            if !app.p.is_set(AppSettings::DisableVersion) && !has_long("version") {
+                let short = Some('V').filter(|x| !has_short(*x));
+                let k = self.arg(
+                    path,
+                    "version",
+                    "version",
+                    (short, Some("version")),
+                    "--version".into(),
+                )?;
+                row.args.push(k);
+                row.longs.push(k);
+                named.push(("version", k));
+            }
+            row.help_subcommand =
+                !app.p.subcommands.is_empty() && !app.p.is_set(AppSettings::DisableHelpSubcommand);
+        }
+
+        row.longs.extend(opt_longs);
+        named.sort();
+        row.usage_order = row.positionals.clone();
+        row.usage_order.extend(named.iter().map(|(_, k)| *k));
+
+        if !app.p.subcommands.is_empty() {
+            row.subcommand_var = self.var(path, "subcommand", None)?;
+        }
+        self.apps[id] = row;
+        for x in app.p.subcommands.iter() {
+            path.push(x.get_name());
+            let sub_id = self.app(x, path)?;
+            path.pop();
+            self.apps[id]
+                .subcommands
+                .push((x.get_name().to_owned(), sub_id));
+        }
+        Ok(id)
+    }
+}
+
+// A bash array of the escaped `values`.
+fn array(values: impl Iterator<Item = String>) -> String {
+    let values = values
+        .map(|x| Shell::Bash.str_escape(&x))
+        .collect::<Vec<_>>();
+    format!("({})", values.join(" "))
+}
+
+fn ids(ids: &[usize]) -> String {
+    ids.iter()
+        .map(|x| x.to_string())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn bit(b: bool) -> String {
+    if b { "1" } else { "0" }.into()
+}
+
+/// Generates a bash script that parses its arguments like `slap parse bash` would with the clap
+/// `app`, setting the same variables. Only part of what clap supports can be compiled, the
+/// other keys of the YAML config are errors.
+pub(crate) fn bash(
+    app: &App,
+    extensions: &Extensions,
+    var_prefix: Option<&str>,
+    messages: &mut Messages<'_>,
+) -> Result<String, Error> {
+    let prefix = match var_prefix {
+        Some(x) => Shell::Bash
+            .ident_check(x, &IdentType::Head)
+            .map_err(Error::Codegen)?,
+        None => "",
+    };
+    let var_names = VarNames::default();
+    var_names
+        .check_collisions(app, prefix)
+        .map_err(Error::Config)?;
+    let bin_name = app.p.meta.bin_name.as_deref().unwrap_or(&app.p.meta.name);
+
+    let mut compiler = Compiler {
+        extensions,
+        var_names,
+        prefix,
+        bin_name,
+        messages,
+        args: Vec::new(),
+        apps: Vec::new(),
+    };
+    compiler.app(app, &mut Vec::new())?;
+    let success_var = compiler.var(&[], "success", None)?;
+    let (args, apps) = (&compiler.args, &compiler.apps);
+
+    let tables: Vec<(&str, String)> = vec![
+        ("__slap_kind", array(args.iter().map(|x| x.kind.into()))),
+        (
+            "__slap_short",
+            array(
+                args.iter()
+                    .map(|x| x.short.map(String::from).unwrap_or_default()),
+            ),
+        ),
+        (
+            "__slap_long",
+            array(args.iter().map(|x| x.long.clone().unwrap_or_default())),
+        ),
+        (
+            "__slap_multiple",
+            array(args.iter().map(|x| bit(x.multiple))),
+        ),
+        (
+            "__slap_required",
+            array(args.iter().map(|x| bit(x.required))),
+        ),
+        ("__slap_hyphen", array(args.iter().map(|x| bit(x.hyphen)))),
+        ("__slap_listed", array(args.iter().map(|x| bit(x.listed)))),
+        // Surrounded by newlines, so a value can be looked for with a single pattern.
+        (
+            "__slap_possible",
+            array(args.iter().map(|x| match x.possible.as_slice() {
+                [] => String::new(),
+                xs => format!("\n{}\n", xs.join("\n")),
+            })),
+        ),
+        // Sorted like clap lists them.
+        (
+            "__slap_possible_list",
+            array(args.iter().map(|x| {
+                let mut possible = x.possible.clone();
+                possible.sort();
+                possible.join(", ")
+            })),
+        ),
+        (
+            "__slap_type",
+            array(args.iter().map(|x| {
+                match x.value_type {
+                    ValueType::Bool => "bool",
+                    ValueType::Int => "int",
+                    ValueType::String => "string",
+                }
+                .into()
+            })),
+        ),
+        ("__slap_name", array(args.iter().map(|x| x.display.clone()))),
+        (
+            "__slap_occurs_var",
+            array(args.iter().map(|x| x.vars[0].clone())),
+        ),
+        (
+            "__slap_indices_var",
+            array(args.iter().map(|x| x.vars[1].clone())),
+        ),
+        (
+            "__slap_vals_var",
+            array(args.iter().map(|x| x.vars[2].clone())),
+        ),
+        (
+            "__slap_count_var",
+            array(args.iter().map(|x| x.vars[3].clone())),
+        ),
+        ("__slap_app_args", array(apps.iter().map(|x| ids(&x.args)))),
+        (
+            "__slap_app_positionals",
+            array(apps.iter().map(|x| ids(&x.positionals))),
+        ),
+        (
+            "__slap_app_usage_order",
+            array(apps.iter().map(|x| ids(&x.usage_order))),
+        ),
+        (
+            "__slap_app_longs",
+            array(apps.iter().map(|x| ids(&x.longs))),
+        ),
+        (
+            "__slap_app_subcommands",
+            array(apps.iter().map(|x| {
+                let names = x.subcommands.iter().map(|(name, _)| name.as_str());
+                names.collect::<Vec<_>>().join(" ")
+            })),
+        ),
+        (
+            "__slap_app_subapps",
+            array(
+                apps.iter()
+                    .map(|x| ids(&x.subcommands.iter().map(|(_, id)| *id).collect::<Vec<_>>())),
+            ),
+        ),
+        (
+            "__slap_app_bin",
+            array(apps.iter().map(|x| x.bin_name.clone())),
+        ),
+        (
+            "__slap_app_usage",
+            array(apps.iter().map(|x| x.usage.clone())),
+        ),
+        (
+            "__slap_app_usage_var",
+            array(apps.iter().map(|x| x.usage_var.clone())),
+        ),
+        (
+            "__slap_app_subcommand_var",
+            array(apps.iter().map(|x| x.subcommand_var.clone())),
+        ),
+        (
+            "__slap_app_help",
+            array(apps.iter().map(|x| x.help.clone())),
+        ),
+        (
+            "__slap_app_version",
+            array(apps.iter().map(|x| x.version.clone())),
+        ),
+        (
+            "__slap_app_help_arg",
+            array(
+                apps.iter()
+                    .map(|x| x.help_arg.map(|x| x.to_string()).unwrap_or_default()),
+            ),
+        ),
+        (
+            "__slap_app_version_arg",
+            array(
+                apps.iter()
+                    .map(|x| x.version_arg.map(|x| x.to_string()).unwrap_or_default()),
+            ),
+        ),
+        ("__slap_app_role", array(apps.iter().map(|x| x.role.into()))),
+        (
+            "__slap_app_help_subcommand",
+            array(apps.iter().map(|x| bit(x.help_subcommand))),
+        ),
+        ("__slap_success_var", Shell::Bash.str_escape(&success_var)),
+    ];
+
+    let mut script = String::from(
+        "# Generated by `slap compile bash`, parses the arguments it's sourced with:\n\
+         #     source parse_args.sh \"$@\"\n\
+         # It sets the variables that `eval \"$(slap parse bash ...)\"` would. Help and version\n\
+         # messages are printed to stdout and errors to stderr, then the script exits.\n\n",
+    );
+    for (name, value) in tables {
+        script.push_str(&format!("{}={}\n", name, value));
+    }
+    script.push('\n');
+    script.push_str(BASH_PARSER);
+    Ok(script)
+}
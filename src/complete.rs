@@ -0,0 +1,637 @@
+use {
+    crate::{
+        extensions::{ArgExtension, Extensions, ValueHint},
+        Error, Shell,
+    },
+    anyhow::Context,
+    clap::{App, ArgSettings},
+};
+
+// The functions added to the bash completions script of clap. `{FUNCTION}_slap_kind` finds the
+// argument being completed like `value_arg`, in the tables generated from the config, and sets
+// `kind` to how its values are completed: its `value_hint`, `values` for a `complete_command`, or
+// `-` to use the completions of clap. `{FUNCTION}` is the completion function of clap.
+const BASH_HOOK: &str = r#"{FUNCTION}_slap_opt() {
+    case $1 in
+{OPTS}        *) return 1 ;;
+    esac
+}
+
+{FUNCTION}_slap_subcommand() {
+    case $1 in
+{SUBCOMMANDS}        *) return 1 ;;
+    esac
+}
+
+{FUNCTION}_slap_positional() {
+    case $1 in
+{POSITIONALS}        *) return 1 ;;
+    esac
+}
+
+{FUNCTION}_slap_kind() {
+    local app= word record pending= pos=1 trailing= after_long= separator i j
+    local cur=${COMP_WORDS[COMP_CWORD]}
+    kind=-
+    for ((i = 1; i < COMP_CWORD; i++)); do
+        word=${COMP_WORDS[i]}
+        # The `=` of `--opt=` is a word of its own.
+        separator=
+        [[ -n $after_long && $word == = ]] && separator=1
+        after_long=
+        if [[ -n $pending ]]; then
+            [[ -n $separator ]] && continue
+            record=$pending pending=
+            if [[ ${record:1:1} == 1 || $word != -?* ]]; then
+                [[ ${record:0:1} == 1 ]] && pending=$record
+                continue
+            fi
+        fi
+        [[ -n $trailing ]] && continue
+        case $word in
+            --) trailing=1 ;;
+            --*=*) ;;
+            --*)
+                {FUNCTION}_slap_opt "$app/$word" && pending=$record
+                after_long=1
+                ;;
+            -?*)
+                for ((j = 1; j < ${#word}; j++)); do
+                    if {FUNCTION}_slap_opt "$app/-${word:j:1}"; then
+                        ((j == ${#word} - 1)) && pending=$record
+                        break
+                    fi
+                done
+                ;;
+            *)
+                if {FUNCTION}_slap_subcommand "$app/$word"; then
+                    app=$record pos=1
+                elif {FUNCTION}_slap_positional "$app/$pos" && [[ ${record:0:1} == 0 ]]; then
+                    pos=$((pos + 1))
+                fi
+                ;;
+        esac
+    done
+    if [[ -n $pending ]] && [[ ${pending:1:1} == 1 || $cur != -?* ]]; then
+        kind=${pending:2}
+    elif [[ -n $trailing || $cur != -* ]] && {FUNCTION}_slap_positional "$app/$pos"; then
+        kind=${record:1}
+    fi
+}
+
+{FUNCTION}_slap_complete() {
+    local kind cur=${COMP_WORDS[COMP_CWORD]}
+    # The `=` of `--opt=` is a word of its own, the value after it is empty.
+    if [[ $cur == = && ${COMP_WORDS[COMP_CWORD - 1]} == --* ]]; then
+        cur=
+    fi
+    {FUNCTION}_slap_kind
+    COMPREPLY=()
+    # `read` splits the lines without expanding the globs they contain.
+    case $kind in
+{VALUES}        file)
+            compopt -o filenames 2>/dev/null
+            IFS=$'\n' read -r -d '' -a COMPREPLY < <(compgen -f -- "$cur")
+            return 0
+            ;;
+        dir)
+            compopt -o filenames 2>/dev/null
+            IFS=$'\n' read -r -d '' -a COMPREPLY < <(compgen -d -- "$cur")
+            return 0
+            ;;
+        command) IFS=$'\n' read -r -d '' -a COMPREPLY < <(compgen -c -- "$cur"); return 0 ;;
+        hostname) IFS=$'\n' read -r -d '' -a COMPREPLY < <(compgen -A hostname -- "$cur"); return 0 ;;
+        user) IFS=$'\n' read -r -d '' -a COMPREPLY < <(compgen -u -- "$cur"); return 0 ;;
+    esac
+    {FUNCTION} "$@"
+}
+
+complete -F {FUNCTION}_slap_complete -o bashdefault -o default {NAME}"#;
+
+// The `values` branch of `{FUNCTION}_slap_complete`, that asks `slap complete` for the candidates
+// printed by the `complete_command`, after a `values` line. `{CONFIG}` is the YAML config.
+const BASH_VALUES: &str = r#"        values)
+            local output
+            if output=$(slap complete -- "${COMP_WORDS[@]:1:COMP_CWORD}" <<< {CONFIG}); then
+                IFS=$'\n' read -r -d '' -a COMPREPLY <<< "${output#values}"
+                return 0
+            fi
+            ;;
+"#;
+
+// The action of the arguments with a `complete_command` in the specs of `_arguments`, called with
+// the subcommand path of the argument. `words` holds the words of the subcommand.
+const ZSH_VALUES: &str = r#"{FUNCTION}_slap_values() {
+    local output
+    output=$(slap complete -- "$@" "${(@Q)words[2,CURRENT]}" <<< {CONFIG}) || return
+    local -a candidates
+    candidates=(${(f)${output#values}})
+    compadd -a candidates
+}"#;
+
+// Like the bash functions, `__{NAME}_slap_kind` prints how the current token is completed. The
+// paths are completed by `__{NAME}_slap_complete`, so they aren't mixed with the other candidates.
+const FISH_HOOK: &str = r#"function __{NAME}_slap_kind
+    set -l opts{OPTS}
+    set -l opt_records{OPT_RECORDS}
+    set -l subcommands{SUBCOMMANDS}
+    set -l subcommand_apps{SUBCOMMAND_APPS}
+    set -l positionals{POSITIONALS}
+    set -l positional_records{POSITIONAL_RECORDS}
+    set -l words (commandline -opc)
+    set -e words[1]
+    set -l current (commandline -ct)
+    set -l app ''
+    set -l pending ''
+    set -l pos 1
+    set -l trailing 0
+    set -l k
+    for word in $words
+        if test -n "$pending"
+            set -l record $pending
+            set pending ''
+            if string match -q -- '?1*' $record; or not string match -q -- '-?*' $word
+                string match -q -- '1*' $record; and set pending $record
+                continue
+            end
+        end
+        test $trailing = 1; and continue
+        if test "$word" = --
+            set trailing 1
+        else if string match -q -- '--*=*' $word
+            continue
+        else if string match -q -- '--*' $word
+            set k (contains -i -- "$app/$word" $opts); and set pending $opt_records[$k]
+        else if string match -q -- '-?*' $word
+            set -l shorts (string sub -s 2 -- $word | string split '')
+            for j in (seq (count $shorts))
+                if set k (contains -i -- "$app/-$shorts[$j]" $opts)
+                    test $j = (count $shorts); and set pending $opt_records[$k]
+                    break
+                end
+            end
+        else if set k (contains -i -- "$app/$word" $subcommands)
+            set app $subcommand_apps[$k]
+            set pos 1
+        else if set k (contains -i -- "$app/$pos" $positionals)
+            string match -q -- '0*' $positional_records[$k]; and set pos (math $pos + 1)
+        end
+    end
+    if test -n "$pending"; and begin; string match -q -- '?1*' $pending; or not string match -q -- '-?*' "$current"; end
+        string sub -s 3 -- $pending
+    else if test $trailing = 1; or not string match -q -- '-*' "$current"; and set k (contains -i -- "$app/$pos" $positionals)
+        string sub -s 2 -- $positional_records[$k]
+    else
+        echo -
+    end
+end
+
+function __{NAME}_slap_complete
+    set -l current (commandline -ct)
+    switch (__{NAME}_slap_kind)
+{VALUES}        case file
+            __fish_complete_path "$current"
+        case dir
+            __fish_complete_directories "$current"
+        case command
+            __fish_complete_command
+        case hostname
+            __fish_print_hostnames
+        case user
+            __fish_complete_users
+        case '*'
+            string match -q -- '-*' "$current"; or __fish_complete_path "$current"
+    end
+end
+
+complete -c {NAME} -f -a '(__{NAME}_slap_complete)'"#;
+
+const FISH_VALUES: &str = r#"        case values
+            set -l words (commandline -opc)
+            set -e words[1]
+            set -l output (printf '%s\n' {CONFIG} | slap complete -- $words "$current")
+            or begin
+                string match -q -- '-*' "$current"; or __fish_complete_path "$current"
+                return
+            end
+            set -e output[1]
+            printf '%s\n' $output
+"#;
+
+// The argument that the last of `words` is a value of, with the subcommand path of its app.
+// `words` are the words of the command line after the program name, the last one is being
+// completed. It follows the way clap assigns the values, without validating the arguments.
+pub(crate) fn value_arg<'a>(
+    mut app: &'a App<'a, 'a>,
+    words: &[String],
+) -> Option<(Vec<&'a str>, &'a str)> {
+    let (current, words) = words.split_last()?;
+    let mut path = Vec::new();
+    // The index in the options of `app` of the one that takes the next word as a value.
+    let mut pending: Option<usize> = None;
+    let mut pos = 1;
+    let mut trailing = false;
+    // Whether the last word is a long option, that bash splits from its value at the `=` of
+    // COMP_WORDBREAKS: `--opt=x` is completed as `--opt`, `=` and `x`.
+    let mut after_long = false;
+
+    let is_new_arg = |word: &str, hyphen: bool| !hyphen && word.len() > 1 && word.starts_with('-');
+
+    for word in words {
+        let separator = std::mem::take(&mut after_long) && word == "=";
+        if let Some(k) = pending.take() {
+            if separator {
+                pending = Some(k);
+                continue;
+            }
+            let opt = &app.p.opts[k];
+            if !is_new_arg(word, opt.b.is_set(ArgSettings::AllowLeadingHyphen)) {
+                if opt.b.is_set(ArgSettings::Multiple) {
+                    pending = Some(k);
+                }
+                continue;
+            }
+        }
+        if trailing {
+            continue;
+        }
+        if word == "--" {
+            trailing = true;
+        } else if let Some(long) = word.strip_prefix("--") {
+            if !long.contains('=') {
+                pending = app.p.opts.iter().position(|x| x.s.long == Some(long));
+                after_long = true;
+            }
+        } else if let Some(shorts) = word.strip_prefix('-').filter(|x| !x.is_empty()) {
+            for (i, c) in shorts.char_indices() {
+                if let Some(k) = app.p.opts.iter().position(|x| x.s.short == Some(c)) {
+                    if i + c.len_utf8() == shorts.len() {
+                        pending = Some(k);
+                    }
+                    break;
+                }
+            }
+        } else if let Some(subcommand) = app.p.subcommands.iter().find(|x| x.get_name() == word) {
+            app = subcommand;
+            path.push(subcommand.get_name());
+            pos = 1;
+        } else if app
+            .p
+            .positionals
+            .get(pos)
+            .is_some_and(|x| !x.b.is_set(ArgSettings::Multiple))
+        {
+            pos += 1;
+        }
+    }
+
+    if let Some(opt) = pending.map(|k| &app.p.opts[k]) {
+        if !is_new_arg(current, opt.b.is_set(ArgSettings::AllowLeadingHyphen)) {
+            return Some((path, opt.b.name));
+        }
+    }
+    if trailing || !current.starts_with('-') {
+        return app.p.positionals.get(pos).map(|x| (path, x.b.name));
+    }
+    None
+}
+
+// The start of the value being completed, the last of `words`. It's empty when it's the `=` that
+// bash splits from the long option before it, like `value_arg` reads it.
+pub(crate) fn value_prefix(words: &[String]) -> &str {
+    match words {
+        [.., long, current] if current == "=" && long.starts_with("--") && !long.contains('=') => {
+            ""
+        }
+        [.., current] => current,
+        [] => "",
+    }
+}
+
+// How the values of argument `name` of the subcommand `path` are completed, in the tables of the
+// bash and fish scripts: with its `value_hint`, with the candidates of its `complete_command`
+// (`values`), or by the completions of clap (`-`).
+fn kind(extensions: &Extensions, path: &[&str], name: &str) -> &'static str {
+    match extensions.arg(path, name) {
+        Some(ArgExtension {
+            value_hint: Some(x),
+            ..
+        }) => x.name(),
+        Some(ArgExtension {
+            complete_command: Some(_),
+            ..
+        }) => "values",
+        _ => "-",
+    }
+}
+
+// The arguments of an app and its subcommands, that the bash and fish scripts read to find the
+// argument being completed. The keys are the subcommand path joined with spaces, then a `/` and
+// an option, a subcommand name or the index of a positional.
+#[derive(Default)]
+struct Tables {
+    // The keys of the short and long option, with the record of the option: `1` if it takes
+    // multiple values or else `0`, `1` if they can start with a hyphen or else `0`, then its kind.
+    opts: Vec<(Vec<String>, String)>,
+    // The keys of the subcommands, with their path.
+    subcommands: Vec<(String, String)>,
+    // The keys of the positionals, with their record: like the options without the hyphen.
+    positionals: Vec<(String, String)>,
+}
+
+impl Tables {
+    fn new(app: &App, extensions: &Extensions) -> Self {
+        let mut tables = Self::default();
+        tables.add(app, extensions, &mut Vec::new());
+        tables
+    }
+
+    fn add<'a>(&mut self, app: &'a App, extensions: &Extensions, path: &mut Vec<&'a str>) {
+        let prefix = format!("{}/", path.join(" "));
+        for opt in &app.p.opts {
+            let keys = opt
+                .s
+                .short
+                .map(|x| format!("{}-{}", prefix, x))
+                .into_iter()
+                .chain(opt.s.long.map(|x| format!("{}--{}", prefix, x)))
+                .collect();
+            let record = format!(
+                "{}{}{}",
+                u8::from(opt.b.is_set(ArgSettings::Multiple)),
+                u8::from(opt.b.is_set(ArgSettings::AllowLeadingHyphen)),
+                kind(extensions, path, opt.b.name)
+            );
+            self.opts.push((keys, record));
+        }
+        for (index, positional) in &app.p.positionals {
+            let record = format!(
+                "{}{}",
+                u8::from(positional.b.is_set(ArgSettings::Multiple)),
+                kind(extensions, path, positional.b.name)
+            );
+            self.positionals
+                .push((format!("{}{}", prefix, index), record));
+        }
+        for subcommand in &app.p.subcommands {
+            path.push(subcommand.get_name());
+            self.subcommands.push((
+                format!("{}{}", prefix, subcommand.get_name()),
+                path.join(" "),
+            ));
+            self.add(subcommand, extensions, path);
+            path.pop();
+        }
+    }
+}
+
+// Makes the completions `script` generated by clap for `shell` complete the values of the
+// arguments with a `value_hint` or a `complete_command`. The values of a `complete_command` are
+// asked to `slap complete`, `config` is the YAML config embedded in the script for it.
+pub(crate) fn hook(
+    script: String,
+    shell: &Shell,
+    config: &str,
+    app: &App,
+    extensions: &Extensions,
+) -> Result<String, Error> {
+    let complete_command = extensions.find_arg(|x| x.complete_command.is_some());
+    let config = shell.str_escape(config);
+    match shell {
+        // `url` has no branch in the bash and fish functions, its values are completed like the
+        // ones of the arguments without a hint: by clap in bash and as paths in fish.
+        Shell::Bash => {
+            bash(&script, app, extensions, complete_command.map(|_| config)).map_err(Error::Codegen)
+        }
+        Shell::Fish => {
+            fish(&script, app, extensions, complete_command.map(|_| config)).map_err(Error::Codegen)
+        }
+        Shell::Zsh => {
+            zsh(&script, app, extensions, complete_command.map(|_| config)).map_err(Error::Codegen)
+        }
+        // The static script of clap, that completes the values of the hints and of the
+        // `complete_command` like the ones of the other arguments.
+        _ => Ok(script),
+    }
+}
+
+// `config` is the escaped YAML config, if `slap complete` is called.
+fn bash(
+    script: &str,
+    app: &App,
+    extensions: &Extensions,
+    config: Option<String>,
+) -> anyhow::Result<String> {
+    let complete = script
+        .lines()
+        .rev()
+        .find_map(|x| x.strip_prefix("complete -F "))
+        .context("Unexpected bash completions script")?
+        .split(' ')
+        .collect::<Vec<_>>();
+    let (function, name) = (complete[0], complete[complete.len() - 1]);
+    let tables = Tables::new(app, extensions);
+    let case = |keys: &[String], value: &str| {
+        let patterns = keys
+            .iter()
+            .map(|x| Shell::Bash.str_escape(x))
+            .collect::<Vec<_>>();
+        format!("        {}) record={} ;;\n", patterns.join(" | "), value)
+    };
+    let opts = tables
+        .opts
+        .iter()
+        .filter(|(keys, _)| !keys.is_empty())
+        .map(|(keys, record)| case(keys, record))
+        .collect::<String>();
+    let subcommands = tables
+        .subcommands
+        .iter()
+        .map(|(key, path)| case(std::slice::from_ref(key), &Shell::Bash.str_escape(path)))
+        .collect::<String>();
+    let positionals = tables
+        .positionals
+        .iter()
+        .map(|(key, record)| case(std::slice::from_ref(key), record))
+        .collect::<String>();
+    let values = config.map_or(String::new(), |x| BASH_VALUES.replace("{CONFIG}", &x));
+    let hook = BASH_HOOK
+        .replace("{OPTS}", &opts)
+        .replace("{SUBCOMMANDS}", &subcommands)
+        .replace("{POSITIONALS}", &positionals)
+        .replace("{VALUES}", &values)
+        .replace("{FUNCTION}", function)
+        .replace("{NAME}", name);
+    Ok(format!("{}\n\n{}", script, hook))
+}
+
+fn fish(
+    script: &str,
+    app: &App,
+    extensions: &Extensions,
+    config: Option<String>,
+) -> anyhow::Result<String> {
+    let name = script
+        .lines()
+        .next()
+        .and_then(|x| x.strip_prefix("complete -c "))
+        .and_then(|x| x.split(' ').next())
+        .context("Unexpected fish completions script")?;
+    let tables = Tables::new(app, extensions);
+    // The items of a list, each one after a space.
+    let list = |xs: &mut dyn Iterator<Item = &String>| {
+        xs.map(|x| format!(" {}", Shell::Fish.str_escape(x)))
+            .collect::<String>()
+    };
+    let opts = tables
+        .opts
+        .iter()
+        .flat_map(|(keys, record)| keys.iter().map(move |x| (x, record)))
+        .collect::<Vec<_>>();
+    let values = config.map_or(String::new(), |x| FISH_VALUES.replace("{CONFIG}", &x));
+    let hook = FISH_HOOK
+        .replace("{OPTS}", &list(&mut opts.iter().map(|x| x.0)))
+        .replace("{OPT_RECORDS}", &list(&mut opts.iter().map(|x| x.1)))
+        .replace(
+            "{SUBCOMMANDS}",
+            &list(&mut tables.subcommands.iter().map(|x| &x.0)),
+        )
+        .replace(
+            "{SUBCOMMAND_APPS}",
+            &list(&mut tables.subcommands.iter().map(|x| &x.1)),
+        )
+        .replace(
+            "{POSITIONALS}",
+            &list(&mut tables.positionals.iter().map(|x| &x.0)),
+        )
+        .replace(
+            "{POSITIONAL_RECORDS}",
+            &list(&mut tables.positionals.iter().map(|x| &x.1)),
+        )
+        .replace("{VALUES}", &values)
+        .replace("{NAME}", name);
+    Ok(format!("{}\n\n{}", script, hook))
+}
+
+// Adds the native completers of the values to the specs of `_arguments` in the zsh script:
+// `'--out=[help]'` becomes `'--out=[help]: :_files'`, `':target:_files'` becomes
+// `':target:_hosts'`. The `case` blocks of the subcommands start with their name, like
+// `(rollback)`, followed by their `_arguments`, the ones of the states of `_arguments` with
+// `words=`, and both end with `;;`.
+fn zsh(
+    script: &str,
+    app: &App,
+    extensions: &Extensions,
+    config: Option<String>,
+) -> anyhow::Result<String> {
+    let (script, last) = script
+        .rsplit_once('\n')
+        .context("Unexpected zsh completions script")?;
+    let function = last
+        .strip_suffix(" \"$@\"")
+        .context("Unexpected zsh completions script")?;
+    let lines = script.lines().collect::<Vec<_>>();
+    let mut patched = Vec::with_capacity(lines.len());
+    // The names of the subcommands of the enclosing blocks, `None` for the states.
+    let mut blocks: Vec<Option<&str>> = Vec::new();
+    for (i, line) in lines.iter().enumerate() {
+        let trimmed = line.trim();
+        let next = lines.get(i + 1).map_or("", |x| x.trim_start());
+        match trimmed.strip_prefix('(').and_then(|x| x.strip_suffix(')')) {
+            Some(name) if next.starts_with("_arguments ") => blocks.push(Some(name)),
+            Some(_) if next.starts_with("words=") => blocks.push(None),
+            _ if trimmed == ";;" => {
+                blocks.pop();
+            }
+            _ => {}
+        }
+        let path = blocks.iter().flatten().copied().collect::<Vec<_>>();
+        let action = zsh_spec_arg(trimmed, app, &path).and_then(|name| {
+            let extension = extensions.arg(&path, name)?;
+            match (extension.value_hint, &config) {
+                (Some(hint), _) => Some(zsh_action(hint).to_owned()),
+                (None, Some(_)) if extension.complete_command.is_some() => {
+                    let args = path
+                        .iter()
+                        .map(|x| format!(" {}", Shell::Zsh.str_escape(x).replace('\'', r"'\''")))
+                        .collect::<String>();
+                    Some(format!("{{{}_slap_values{}}}", function, args))
+                }
+                _ => None,
+            }
+        });
+        let line = match action {
+            Some(action) if line.ends_with("]' \\") => {
+                format!("{}: :{}' \\", &line[..line.len() - 3], action)
+            }
+            Some(action) if line.ends_with(":_files' \\") => {
+                format!("{}{}' \\", &line[..line.len() - 9], action)
+            }
+            _ => line.to_string(),
+        };
+        patched.push(line);
+    }
+    let values = config.map_or(String::new(), |x| {
+        format!(
+            "{}\n\n",
+            ZSH_VALUES
+                .replace("{FUNCTION}", function)
+                .replace("{CONFIG}", &x)
+        )
+    });
+    // The script is autoloaded, it ends by calling the completion function.
+    Ok(format!("{}\n{}{}", patched.join("\n"), values, last))
+}
+
+// The name of the option or positional argument of `app` at `path` that the `_arguments` spec
+// `line` completes the values of.
+fn zsh_spec_arg<'a>(line: &str, app: &'a App, path: &[&str]) -> Option<&'a str> {
+    let app = path.iter().try_fold(app, |app, name| {
+        app.p.subcommands.iter().find(|x| x.get_name() == *name)
+    })?;
+    let spec = line.strip_prefix('\'')?.strip_suffix("' \\")?;
+    // The conflicting arguments, then `*` if the argument takes multiple values.
+    let spec = match spec.strip_prefix('(') {
+        Some(x) => &x[x.find(')')? + 1..],
+        None => spec,
+    };
+    let spec = spec.strip_prefix('*').unwrap_or(spec);
+    if let Some(x) = spec.strip_prefix("--") {
+        let (long, _) = x.split_once("=[")?;
+        app.p
+            .opts
+            .iter()
+            .find(|x| x.s.long == Some(long))
+            .map(|x| x.b.name)
+    } else if let Some(x) = spec.strip_prefix('-') {
+        let short = x.chars().next()?;
+        if !x[short.len_utf8()..].starts_with("+[") {
+            return None;
+        }
+        app.p
+            .opts
+            .iter()
+            .find(|x| x.s.short == Some(short))
+            .map(|x| x.b.name)
+    } else {
+        let x = spec.strip_prefix(':')?;
+        let x = x.strip_prefix(':').unwrap_or(x);
+        let name = x.split(':').next()?.split(" -- ").next()?;
+        app.p
+            .positionals
+            .values()
+            .find(|x| x.b.name == name)
+            .map(|x| x.b.name)
+    }
+}
+
+fn zsh_action(hint: ValueHint) -> &'static str {
+    match hint {
+        ValueHint::Command => "_command_names -e",
+        ValueHint::Dir => "_files -/",
+        ValueHint::File => "_files",
+        ValueHint::Hostname => "_hosts",
+        ValueHint::Url => "_urls",
+        ValueHint::User => "_users",
+    }
+}
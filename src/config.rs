@@ -0,0 +1,370 @@
+use {
+    crate::{
+        app_wrapper::AppWrapper,
+        compile, complete, config_checker,
+        extensions::Extensions,
+        message_cache::{fnv1a, MessageCache},
+        Error, ParseOptions, Shell, VarNames,
+    },
+    anyhow::{anyhow, Context},
+    clap::{App, Arg, SubCommand, YamlLoader},
+    std::{ffi::OsString, path::Path},
+    yaml_rust::Yaml,
+};
+
+/// A loaded and checked YAML config.
+pub struct Config {
+    // The whole YAML config.
+    yaml: Yaml,
+    // The YAML config without the `subcommands` entry, see `Config::app`.
+    app_yaml: Yaml,
+    extensions: Extensions,
+    // The YAML config as it was received, the key of the message cache.
+    source: String,
+    message_cache: Option<MessageCache>,
+}
+
+impl Config {
+    /// Loads the YAML config and checks it for the keys that clap doesn't handle well.
+    pub fn load(s: &str) -> Result<Self, Error> {
+        Self::load_from(s, None)
+    }
+
+    /// Like `load`, but the config is cached in `dir`: the checks it passed and its rendered
+    /// help and version messages aren't done again by later runs with the same config.
+    pub fn load_cached(s: &str, dir: &Path) -> Result<Self, Error> {
+        Self::load_from(s, Some(dir))
+    }
+
+    fn load_from(s: &str, cache_dir: Option<&Path>) -> Result<Self, Error> {
+        if s.is_empty() {
+            return Err(Error::Config(anyhow!(
+                "Received an empty string from STDIN. Check that the YAML config file exists"
+            )));
+        }
+
+        Self::from_yaml(Self::load_yaml(s)?, s.into(), cache_dir)
+    }
+
+    // The first YAML document of `s`.
+    pub(crate) fn load_yaml(s: &str) -> Result<Yaml, Error> {
+        YamlLoader::load_from_str(s)
+            .map_err(|e| Error::Config(e.into()))?
+            .into_iter()
+            .next()
+            .context("Invalid YAML config")
+            .map_err(Error::Config)
+    }
+
+    // Checks the YAML config loaded from `source`, cached in `cache_dir` if there is one.
+    pub(crate) fn from_yaml(
+        mut yaml: Yaml,
+        source: String,
+        cache_dir: Option<&Path>,
+    ) -> Result<Self, Error> {
+        let extensions = Extensions::strip(&mut yaml).map_err(Error::Config)?;
+        let yaml_config = yaml
+            .clone()
+            .into_hash()
+            .context("Invalid YAML config")
+            .map_err(Error::Config)?;
+        config_checker::required(&yaml_config).map_err(Error::Config)?;
+        config_checker::banned(&yaml_config).map_err(Error::Config)?;
+
+        let app_yaml = {
+            let mut yaml_config = yaml_config;
+            yaml_config.remove(&Yaml::String("subcommands".into()));
+            Yaml::Hash(yaml_config)
+        };
+
+        let config = Self {
+            yaml,
+            app_yaml,
+            extensions,
+            message_cache: cache_dir.map(|dir| MessageCache::new(dir, &source)),
+            source,
+        };
+        // Checked with the default variable names, whatever command uses the config. The names
+        // given to `parse_with` are checked again there. It builds the app, so it's skipped if
+        // the config already passed it.
+        let cache = config.message_cache.as_ref();
+        if !cache.is_some_and(MessageCache::is_checked) {
+            VarNames::default()
+                .check_collisions(&config.app()?.app.app, "")
+                .map_err(Error::Config)?;
+            if let Some(cache) = cache {
+                cache.set_checked();
+            }
+        }
+        Ok(config)
+    }
+
+    /// A hash of the YAML config `s`, stable between runs, that identifies the config without
+    /// keeping it around.
+    pub fn hash(s: &str) -> u64 {
+        fnv1a(s.as_bytes())
+    }
+
+    /// Caches the rendered help and version messages in `dir`, so they aren't rendered again
+    /// by later runs with the same config.
+    pub fn with_message_cache(mut self, dir: &Path) -> Self {
+        self.message_cache = Some(MessageCache::new(dir, &self.source));
+        self
+    }
+
+    /// Builds the clap app described by the YAML config.
+    pub fn app(&self) -> Result<ExternalApp<'_>, Error> {
+        // Clap doesn't let us redirect --help and --version to stderr so we have to do it
+        // manually. This block of code parses the subcommands into a Vec<AppWrapper>, they are
+        // added manually later to the `external_app` clap::App, that is built from the YAML
+        // config without the subcommands parts.
+        let subcommands = match self.yaml["subcommands"] {
+            Yaml::BadValue => Vec::new(),
+            ref subcommands => {
+                let subcommands = subcommands
+                    .as_vec()
+                    .context("Subcommands object must be an array of maps")
+                    .map_err(Error::Config)?
+                    .iter()
+                    .map(|x| {
+                        let mut app = SubCommand::from_yaml(x);
+                        let mut path = vec![app.get_name().to_owned()];
+                        self.extensions.apply(&mut app, &mut path)?;
+                        Ok(AppWrapper::new(app, |app| app))
+                    });
+                let mut xs = Vec::new();
+                for subcmd in subcommands {
+                    xs.push(subcmd.map_err(Error::Config)?);
+                }
+                xs
+            }
+        };
+
+        let help_subcmd = AppWrapper::new(SubCommand::with_name("help"), |app: App| {
+            app.arg(Arg::with_name("SUBCMD").required(false))
+                .about("Prints this message or the help of the given subcommand(s)")
+        });
+        let mut app = App::from(&self.app_yaml);
+        self.extensions
+            .apply(&mut app, &mut Vec::new())
+            .map_err(Error::Config)?;
+        let name = app.get_name().to_owned();
+        let app = AppWrapper::new(app.bin_name(&name), {
+            let subcommand = help_subcmd.app.clone();
+            let subcommands = subcommands.clone().into_iter().map(|x| x.app);
+            move |app: App| app.subcommand(subcommand).subcommands(subcommands)
+        });
+
+        Ok(ExternalApp {
+            name,
+            app,
+            subcommands,
+            help_subcmd,
+            extensions: &self.extensions,
+            source: &self.source,
+            message_cache: self.message_cache.as_ref(),
+        })
+    }
+}
+
+/// The clap app described by a YAML config.
+pub struct ExternalApp<'a> {
+    name: String,
+    app: AppWrapper<'a, 'a>,
+    subcommands: Vec<AppWrapper<'a, 'a>>,
+    help_subcmd: AppWrapper<'a, 'a>,
+    extensions: &'a Extensions,
+    // The YAML config, embedded in the completions scripts that call `slap complete`.
+    source: &'a str,
+    message_cache: Option<&'a MessageCache>,
+}
+
+impl<'a> ExternalApp<'a> {
+    /// The name of the app, as specified in the YAML config.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    // The message `kind` of the app named `name`, from the cache if there is one.
+    fn message(
+        &self,
+        name: &str,
+        kind: &str,
+        render: impl FnOnce() -> anyhow::Result<String>,
+    ) -> Result<String, Error> {
+        match self.message_cache {
+            Some(cache) => cache.get_or_render(name, kind, render),
+            None => render(),
+        }
+        .map_err(Error::Codegen)
+    }
+
+    fn help(&self, name: &str, app: &AppWrapper) -> Error {
+        match self.message(name, "help", || app.help_msg()) {
+            Ok(msg) => Error::Help(msg),
+            Err(e) => e,
+        }
+    }
+
+    fn version(&self, name: &str, app: &AppWrapper) -> Error {
+        match self.message(name, "version", || app.version_msg()) {
+            Ok(msg) => Error::Version(msg),
+            Err(e) => e,
+        }
+    }
+
+    /// Parses `args` (without the program name) and generates the code for `shell`.
+    pub fn parse(
+        &self,
+        shell: &Shell,
+        args: &[OsString],
+        var_prefix: Option<&str>,
+    ) -> Result<String, Error> {
+        self.parse_with(shell, args, var_prefix, &ParseOptions::default())
+    }
+
+    /// Like `parse`, but the generated code can be changed with `options`.
+    pub fn parse_with(
+        &self,
+        shell: &Shell,
+        args: &[OsString],
+        var_prefix: Option<&str>,
+        options: &ParseOptions,
+    ) -> Result<String, Error> {
+        let var_prefix = match shell {
+            Shell::Template(template) if var_prefix.is_none() => template.var_prefix(),
+            _ => var_prefix,
+        };
+        // Checked before parsing `args`, so a config that can generate the same variable twice
+        // is rejected whatever arguments it receives. The default names are checked by
+        // `Config::load`.
+        options.var_names.check().map_err(Error::Codegen)?;
+        if options.var_names != VarNames::default() {
+            options
+                .var_names
+                .check_collisions(&self.app.app, var_prefix.unwrap_or(""))
+                .map_err(Error::Config)?;
+        }
+
+        let args = std::iter::once(OsString::from(&self.name)).chain(args.iter().cloned());
+        let matches = self.app.app.clone().get_matches_from_safe(args)?;
+
+        // We can't output help or version messages to stdout. Only to stderr.
+        // The only thing that we can output to stdout is the code that the user will eval.
+        if let Some(ref subcmd) = matches.subcommand {
+            let subcmd_matches = &subcmd.matches;
+            let subcmd_name = &subcmd.name;
+
+            let find_subcmd = |name: &str| {
+                self.subcommands
+                    .iter()
+                    .find(|x| x.app.get_name() == name)
+                    .ok_or_else(|| {
+                        Error::Args(clap::Error::with_description(
+                            &format!("The subcommand '{}' wasn't recognized", name),
+                            clap::ErrorKind::InvalidSubcommand,
+                        ))
+                    })
+            };
+
+            if subcmd_name == "help" {
+                if subcmd_matches.is_present("help") {
+                    return Err(self.help("help", &self.help_subcmd));
+                }
+                if subcmd_matches.is_present("version") {
+                    return Err(self.version("help", &self.help_subcmd));
+                }
+                return Err(match subcmd_matches.value_of("SUBCMD") {
+                    Some(help_subcmd) => self.help(help_subcmd, find_subcmd(help_subcmd)?),
+                    None => self.help("", &self.app),
+                });
+            }
+            if subcmd_matches.is_present("help") {
+                return Err(self.help(subcmd_name, find_subcmd(subcmd_name)?));
+            }
+            if subcmd_matches.is_present("version") {
+                return Err(self.version(subcmd_name, find_subcmd(subcmd_name)?));
+            }
+        } else {
+            if matches.is_present("help") {
+                return Err(self.help("", &self.app));
+            }
+            if matches.is_present("version") {
+                return Err(self.version("", &self.app));
+            }
+        }
+
+        shell
+            .parse(&self.app.app, self.extensions, matches, var_prefix, options)
+            .map_err(Error::Codegen)
+    }
+
+    /// Generates a script for `shell` that parses its arguments like `parse` would, for machines
+    /// where slap isn't installed. Only bash is supported.
+    pub fn compile(&self, shell: &Shell, var_prefix: Option<&str>) -> Result<String, Error> {
+        if !matches!(shell, Shell::Bash) {
+            return Err(Error::Codegen(anyhow!("Only bash scripts can be compiled")));
+        }
+        let messages = |name: &str, app: &AppWrapper| -> Result<(String, String), Error> {
+            Ok((
+                self.message(name, "help", || app.help_msg())?,
+                self.message(name, "version", || app.version_msg())?,
+            ))
+        };
+        compile::bash(
+            &self.app.app,
+            self.extensions,
+            var_prefix,
+            &mut |path: &[&str], app: &App| match path {
+                [] => messages("", &self.app),
+                ["help"] => messages("help", &self.help_subcmd),
+                [name] => messages(name, &AppWrapper { app: app.clone() }),
+                // Subcommands of subcommands are rendered by clap, with the whole path as name.
+                _ => {
+                    let mut app = app.clone();
+                    app.p.meta.bin_name = Some(format!("{} {}", self.name, path.join(" ")));
+                    app.p.create_help_and_version();
+                    let app = AppWrapper { app };
+                    let help = app.help_msg().map_err(Error::Codegen)?;
+                    Ok((help, app.version_msg().map_err(Error::Codegen)?))
+                }
+            },
+        )
+    }
+
+    /// Generates a completions script for `shell`.
+    // FIXME: Fix ZSH not generating the code for completion.
+    pub fn completions(&mut self, shell: &Shell) -> Result<String, Error> {
+        let script = self
+            .app
+            .completions_script(&self.name, shell)
+            .map_err(Error::Codegen)?;
+        if !self.extensions.completes_values() {
+            return Ok(script);
+        }
+        complete::hook(script, shell, self.source, &self.app.app, self.extensions)
+    }
+
+    /// The `complete_command` of the argument whose value is the last of `words`, the command
+    /// line after the program name, like `slap complete` receives it.
+    pub fn complete_command(&self, words: &[String]) -> Option<&str> {
+        let (path, name) = complete::value_arg(&self.app.app, words)?;
+        self.extensions
+            .arg(&path, name)?
+            .complete_command
+            .as_deref()
+    }
+
+    /// The `value_hint` of the argument whose value is the last of `words`, like
+    /// `complete_command`.
+    pub fn value_hint(&self, words: &[String]) -> Option<&'static str> {
+        let (path, name) = complete::value_arg(&self.app.app, words)?;
+        Some(self.extensions.arg(&path, name)?.value_hint?.name())
+    }
+
+    /// The start of the value being completed, that the candidates of the `complete_command`
+    /// begin with: the last of `words`, or nothing after the `=` of `--opt=` split by bash.
+    pub fn value_prefix(words: &[String]) -> &str {
+        complete::value_prefix(words)
+    }
+}
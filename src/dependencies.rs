@@ -1,15 +1,137 @@
 use {
-    anyhow::anyhow,
+    anyhow::{anyhow, bail},
     clap::ArgMatches,
+    serde_json::{Map, Value},
     std::{
-        collections::HashMap,
+        env,
+        ffi::OsString,
         fmt::{self, Display, Formatter},
+        fs,
         path::PathBuf,
     },
 };
 
+/// Version of the JSON document printed by `slap deps --all`. Bump it every time a field is
+/// removed or its meaning changes, adding fields is backward compatible.
+pub const SCHEMA_VERSION: u32 = 1;
+
+// Where and how dependencies are searched.
+struct Lookup {
+    // The absolute directories searched, `None` if there is no `$PATH`.
+    paths: Option<OsString>,
+    cwd: PathBuf,
+    // Where dependencies are searched, used in error messages.
+    location: &'static str,
+    // Whether to collect every match instead of stopping at the first one.
+    every: bool,
+}
+
+impl Lookup {
+    fn from_matches(matches: &ArgMatches) -> anyhow::Result<Self> {
+        let cwd = env::current_dir()?;
+        let path = matches.value_of_os("path");
+        let include_cwd = matches.is_present("include_cwd");
+        let mut dirs = Vec::new();
+        if include_cwd {
+            dirs.push(cwd.clone());
+        }
+        if let Some(paths) = path.map(OsString::from).or_else(|| env::var_os("PATH")) {
+            // Relative entries are joined with the working directory, so the paths found are
+            // absolute. Collecting the components drops the `.` ones, like the `./` of
+            // `./vendor/bin`.
+            dirs.extend(
+                env::split_paths(&paths).map(|x| cwd.join(x).components().collect::<PathBuf>()),
+            );
+        }
+        if let Some(dir) = dirs.iter().find(|x| env::join_paths([x]).is_err()) {
+            bail!(
+                "Can't search dependencies in '{}', it contains the separator of path lists",
+                dir.display()
+            );
+        }
+        let paths = if dirs.is_empty() {
+            None
+        } else {
+            Some(env::join_paths(dirs)?)
+        };
+        let location = match (path.is_some(), include_cwd) {
+            (true, true) => "the current directory and the given path list",
+            (true, false) => "the given path list",
+            (false, true) => "the current directory and $PATH",
+            (false, false) => "$PATH",
+        };
+        Ok(Self {
+            paths,
+            cwd,
+            location,
+            every: matches.is_present("every"),
+        })
+    }
+
+    fn find(&self, name: &str) -> Vec<PathBuf> {
+        let paths = self.paths.clone();
+        if !self.every {
+            return which::which_in(name, paths, &self.cwd)
+                .into_iter()
+                .collect();
+        }
+
+        let mut xs: Vec<PathBuf> = Vec::new();
+        for dir in paths.iter().flat_map(env::split_paths) {
+            if let Ok(path) = which::which_in(name, Some(&dir), &self.cwd) {
+                if !xs.contains(&path) {
+                    xs.push(path);
+                }
+            }
+        }
+        xs
+    }
+}
+
+struct Dependency<'a> {
+    name: &'a str,
+    path: Option<PathBuf>,
+    // The path with every symlink resolved.
+    canonical_path: Option<PathBuf>,
+    // Every match in search order, the first one is `path`, the others are shadowed by it.
+    matches: Vec<PathBuf>,
+}
+
+impl<'a> Dependency<'a> {
+    fn new(name: &'a str, lookup: &Lookup) -> Self {
+        let matches = lookup.find(name);
+        let path = matches.first().cloned();
+        let canonical_path = path.as_ref().and_then(|x| fs::canonicalize(x).ok());
+        Self {
+            name,
+            path,
+            canonical_path,
+            matches,
+        }
+    }
+
+    fn status(&self) -> &'static str {
+        if self.path.is_some() {
+            "found"
+        } else {
+            "not_found"
+        }
+    }
+
+    fn to_json(&self) -> Value {
+        serde_json::json!({
+            "name": self.name,
+            "path": self.path.as_ref().map(|x| x.display().to_string()),
+            "canonical_path": self.canonical_path.as_ref().map(|x| x.display().to_string()),
+            "status": self.status(),
+            "matches": self.matches.iter().map(|x| x.display().to_string()).collect::<Vec<_>>(),
+        })
+    }
+}
+
 pub struct Dependencies<'a> {
     failed_deps: Vec<&'a str>,
+    location: &'static str,
 }
 
 impl<'a> Display for Dependencies<'a> {
@@ -19,12 +141,16 @@ impl<'a> Display for Dependencies<'a> {
             1 => {
                 writeln!(
                     f,
-                    "Required dependency '{}' not found in $PATH",
-                    self.failed_deps[0],
+                    "Required dependency '{}' not found in {}",
+                    self.failed_deps[0], self.location,
                 )?;
             }
             _ => {
-                writeln!(f, "These required dependencies were not found in $PATH:")?;
+                writeln!(
+                    f,
+                    "These required dependencies were not found in {}:",
+                    self.location
+                )?;
                 for dep in self.failed_deps.iter() {
                     writeln!(f, "    {}", dep)?;
                 }
@@ -35,12 +161,11 @@ impl<'a> Display for Dependencies<'a> {
 }
 
 impl<'a> Dependencies<'a> {
-    fn parse(deps: &'_ [&'a str]) -> HashMap<&'a str, Option<PathBuf>> {
-        let mut map = HashMap::new();
-        for dep in deps.iter() {
-            map.insert(*dep, which::which(dep).ok());
-        }
-        map
+    // Results are returned in the same order of `deps`, so the output is stable between runs.
+    fn parse(deps: &'_ [&'a str], lookup: &Lookup) -> Vec<Dependency<'a>> {
+        deps.iter()
+            .map(|dep| Dependency::new(dep, lookup))
+            .collect()
     }
 
     #[cfg(feature = "color")]
@@ -71,7 +196,7 @@ impl<'a> Dependencies<'a> {
                 stderr.set_color(color_spec.set_fg(None).set_bold(false))?;
                 write!(&mut stderr, " not found in ")?;
                 stderr.set_color(color_spec.set_fg(Some(Color::Cyan)).set_bold(true))?;
-                writeln!(&mut stderr, "$PATH")?;
+                writeln!(&mut stderr, "{}", self.location)?;
             }
             _ => {
                 write!(&mut stderr, "error: ")?;
@@ -81,7 +206,7 @@ impl<'a> Dependencies<'a> {
                     "These required dependencies were not found in "
                 )?;
                 stderr.set_color(color_spec.set_fg(Some(Color::Cyan)).set_bold(true))?;
-                write!(&mut stderr, "$PATH")?;
+                write!(&mut stderr, "{}", self.location)?;
                 stderr.set_color(color_spec.set_fg(None).set_bold(false))?;
                 writeln!(&mut stderr, ":")?;
                 stderr.set_color(color_spec.set_fg(Some(Color::Green)).set_bold(true))?;
@@ -99,60 +224,74 @@ impl<'a> Dependencies<'a> {
     }
 
     pub fn check(matches: &'a ArgMatches) -> Option<anyhow::Result<()>> {
-        macro_rules! exit {
-            ( $x:expr ) => {
-                return Some(if $x == 0 {
-                    Ok(())
-                } else {
-                    Err(anyhow!(
-                        "1 or more required dependencies were not found in $PATH"
-                    ))
-                });
+        if let Some(matches) = matches.subcommand_matches("deps") {
+            let lookup = match Lookup::from_matches(matches) {
+                Ok(x) => x,
+                Err(e) => {
+                    eprintln!("error: {:#}", e);
+                    return Some(Err(e));
+                }
             };
-        }
 
-        if let Some(matches) = matches.subcommand_matches("deps") {
+            macro_rules! exit {
+                ( $x:expr ) => {
+                    return Some(if $x == 0 {
+                        Ok(())
+                    } else {
+                        Err(anyhow!(
+                            "1 or more required dependencies were not found in {}",
+                            lookup.location
+                        ))
+                    });
+                };
+            }
+
             let deps: Vec<&'a str> = matches
                 .values_of("DEPENDENCIES")
                 .unwrap()
                 .collect::<Vec<_>>();
-            let results = Self::parse(&deps);
+            let results = Self::parse(&deps, &lookup);
 
-            if matches.is_present("succeded") {
+            if matches.is_present("succeeded") {
                 let mut failed_deps = 0;
-                for (_, path) in results {
-                    if let Some(path) = path {
-                        println!("{}", path.display());
-                    } else {
+                for dep in results {
+                    if dep.matches.is_empty() {
                         failed_deps += 1;
                     }
+                    for path in dep.matches {
+                        println!("{}", path.display());
+                    }
                 }
                 exit!(failed_deps);
             }
 
             if matches.is_present("failed") {
                 let mut failed_deps = 0;
-                for (dep, path) in results {
-                    if path.is_none() {
+                for dep in results {
+                    if dep.path.is_none() {
                         failed_deps += 1;
-                        println!("{}", dep);
+                        println!("{}", dep.name);
                     }
                 }
                 exit!(failed_deps);
             }
 
             if matches.is_present("all") {
-                let mut succeded = HashMap::new();
+                let mut succeeded = Map::new();
                 let mut failed = Vec::new();
-                for (k, v) in results {
-                    if let Some(path) = v {
-                        succeded.insert(k, path);
+                for dep in results.iter() {
+                    if let Some(ref path) = dep.path {
+                        succeeded.insert(dep.name.into(), path.display().to_string().into());
                     } else {
-                        failed.push(k);
+                        failed.push(dep.name);
                     }
                 }
                 let json_val = serde_json::json!({
-                    "succeded": succeded,
+                    "schema_version": SCHEMA_VERSION,
+                    "dependencies": results.iter().map(Dependency::to_json).collect::<Vec<_>>(),
+                    "succeeded": succeeded,
+                    // Misspelled key kept for backward compatibility, use `succeeded` instead.
+                    "succeded": succeeded,
                     "failed": failed,
                 });
                 let json_str = if matches.is_present("pretty") {
@@ -166,14 +305,17 @@ impl<'a> Dependencies<'a> {
             }
 
             let mut failed_deps = Vec::new();
-            for (k, v) in results {
-                if v.is_none() {
-                    failed_deps.push(k);
+            for dep in results {
+                if dep.path.is_none() {
+                    failed_deps.push(dep.name);
                 }
             }
             let len = failed_deps.len();
 
-            let s = Self { failed_deps };
+            let s = Self {
+                failed_deps,
+                location: lookup.location,
+            };
             if cfg!(feature = "color") {
                 if let Err(e) = s.print_colored() {
                     return Some(Err(e));
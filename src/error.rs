@@ -0,0 +1,59 @@
+use std::{
+    error,
+    fmt::{self, Display, Formatter},
+    io,
+};
+
+/// Everything that can go wrong while loading a YAML config or parsing arguments with it.
+#[derive(Debug)]
+pub enum Error {
+    /// The YAML config can't be loaded or isn't valid.
+    Config(anyhow::Error),
+    /// The arguments don't conform to the YAML config. The error message is the one that clap
+    /// would print, `clap::Error::exit` prints it and exits like `slap parse` does.
+    Args(clap::Error),
+    /// The arguments requested the help message, which is contained here. Not really an error,
+    /// but no code is generated.
+    Help(String),
+    /// The arguments requested the version message, which is contained here. Not really an
+    /// error, but no code is generated.
+    Version(String),
+    /// The code for the target shell can't be generated, for example because an argument name
+    /// isn't a valid identifier in the target shell.
+    Codegen(anyhow::Error),
+    Io(io::Error),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Self::Config(e) => write!(f, "{:#}", e),
+            Self::Args(e) => write!(f, "{}", e.message),
+            Self::Help(msg) | Self::Version(msg) => write!(f, "{}", msg),
+            Self::Codegen(e) => write!(f, "{:#}", e),
+            Self::Io(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Self::Args(e) => Some(e),
+            Self::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<clap::Error> for Error {
+    fn from(e: clap::Error) -> Self {
+        Self::Args(e)
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Self::Io(e)
+    }
+}
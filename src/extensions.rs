@@ -0,0 +1,259 @@
+use {
+    anyhow::{bail, Context},
+    clap::App,
+    std::{collections::HashMap, rc::Rc},
+    yaml_rust::Yaml,
+};
+
+// Type of the values of an argument, set with the `type` key.
+#[derive(Clone, Copy, PartialEq)]
+pub enum ValueType {
+    Bool,
+    Int,
+    String,
+}
+
+impl ValueType {
+    fn from_yaml(yaml: &Yaml) -> anyhow::Result<Self> {
+        match yaml.as_str() {
+            Some("bool") => Ok(Self::Bool),
+            Some("int") => Ok(Self::Int),
+            Some("string") => Ok(Self::String),
+            _ => bail!("`type` must be one of [\"bool\", \"int\", \"string\"]"),
+        }
+    }
+
+    pub fn parse_bool(s: &str) -> Option<bool> {
+        match s.to_lowercase().as_str() {
+            "true" | "yes" | "on" | "1" => Some(true),
+            "false" | "no" | "off" | "0" => Some(false),
+            _ => None,
+        }
+    }
+
+    pub fn parse_int(s: &str) -> Option<i64> {
+        s.parse().ok()
+    }
+
+    fn validate(self, s: &str) -> Result<(), String> {
+        match self {
+            Self::Bool if Self::parse_bool(s).is_none() => {
+                Err("must be a boolean (true, false, yes, no, on, off, 1, 0)".into())
+            }
+            Self::Int if Self::parse_int(s).is_none() => Err("must be an integer".into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+// The kind of values of an argument, set with the `value_hint` key, completed by the native
+// completers of the shells.
+#[derive(Clone, Copy, PartialEq)]
+pub enum ValueHint {
+    Command,
+    Dir,
+    File,
+    Hostname,
+    Url,
+    User,
+}
+
+impl ValueHint {
+    const ALL: [Self; 6] = [
+        Self::Command,
+        Self::Dir,
+        Self::File,
+        Self::Hostname,
+        Self::Url,
+        Self::User,
+    ];
+
+    fn from_yaml(yaml: &Yaml) -> anyhow::Result<Self> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|x| yaml.as_str() == Some(x.name()))
+            .with_context(|| {
+                format!(
+                    "`value_hint` must be one of {:?}",
+                    Self::ALL.map(Self::name)
+                )
+            })
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Command => "command",
+            Self::Dir => "dir",
+            Self::File => "file",
+            Self::Hostname => "hostname",
+            Self::Url => "url",
+            Self::User => "user",
+        }
+    }
+}
+
+// Keys of an argument handled by slap instead of clap.
+#[derive(Clone, Default)]
+pub struct ArgExtension {
+    pub value_type: Option<ValueType>,
+    // The shell command that prints the candidates of the values, set with `complete_command`.
+    pub complete_command: Option<String>,
+    pub value_hint: Option<ValueHint>,
+}
+
+// The slap-only keys of the arguments of a YAML config, keyed by subcommand path and argument
+// name. Clap panics on keys it doesn't know, so they are stripped before the YAML config is
+// passed to clap.
+#[derive(Clone, Default)]
+pub struct Extensions {
+    args: HashMap<(Vec<String>, String), ArgExtension>,
+}
+
+impl Extensions {
+    // Removes the slap-only keys from the YAML config and collects them.
+    pub fn strip(yaml: &mut Yaml) -> anyhow::Result<Self> {
+        let mut extensions = Self::default();
+        extensions.strip_app(yaml, &mut Vec::new())?;
+        Ok(extensions)
+    }
+
+    fn strip_app(&mut self, yaml: &mut Yaml, path: &mut Vec<String>) -> anyhow::Result<()> {
+        let yaml = match yaml {
+            Yaml::Hash(x) => x,
+            _ => return Ok(()),
+        };
+
+        if let Some(Yaml::Array(args)) = yaml.get_mut(&Yaml::String("args".into())) {
+            for arg in args.iter_mut().filter_map(single_entry) {
+                let (name, arg) = arg;
+                let arg = match arg {
+                    Yaml::Hash(x) => x,
+                    _ => continue,
+                };
+                let mut extension = ArgExtension::default();
+                if let Some(x) = arg.remove(&Yaml::String("type".into())) {
+                    extension.value_type = Some(
+                        ValueType::from_yaml(&x)
+                            .with_context(|| format!("Invalid argument '{}'", name))?,
+                    );
+                }
+                match arg.remove(&Yaml::String("complete_command".into())) {
+                    Some(Yaml::String(x)) => extension.complete_command = Some(x),
+                    Some(_) => bail!(
+                        "Invalid argument '{}': `complete_command` must be a string",
+                        name
+                    ),
+                    None => {}
+                }
+                if let Some(x) = arg.remove(&Yaml::String("value_hint".into())) {
+                    if extension.complete_command.is_some() {
+                        bail!(
+                            "Invalid argument '{}': `value_hint` and `complete_command` can't be used together",
+                            name
+                        );
+                    }
+                    extension.value_hint = Some(
+                        ValueHint::from_yaml(&x)
+                            .with_context(|| format!("Invalid argument '{}'", name))?,
+                    );
+                }
+                self.args.insert((path.clone(), name), extension);
+            }
+        }
+
+        if let Some(Yaml::Array(subcommands)) = yaml.get_mut(&Yaml::String("subcommands".into())) {
+            for (name, subcommand) in subcommands.iter_mut().filter_map(single_entry) {
+                path.push(name);
+                self.strip_app(subcommand, path)?;
+                path.pop();
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn arg(&self, path: &[&str], name: &str) -> Option<&ArgExtension> {
+        let path = path.iter().map(|x| x.to_string()).collect();
+        self.args.get(&(path, name.to_owned()))
+    }
+
+    // Whether the completions scripts complete some values.
+    pub fn completes_values(&self) -> bool {
+        self.find_arg(|x| x.complete_command.is_some() || x.value_hint.is_some())
+            .is_some()
+    }
+
+    // The name of the first argument, by subcommand path then name, whose extension matches `f`.
+    pub fn find_arg(&self, f: impl Fn(&ArgExtension) -> bool) -> Option<&str> {
+        self.args
+            .iter()
+            .filter(|(_, x)| f(x))
+            .map(|(k, _)| k)
+            .min()
+            .map(|(_, name)| name.as_str())
+    }
+
+    // Adds the validators for the values of the typed arguments of `app` and its subcommands.
+    // `path` is the subcommand path of `app`.
+    pub fn apply(&self, app: &mut App, path: &mut Vec<String>) -> anyhow::Result<()> {
+        for ((arg_path, name), extension) in &self.args {
+            if arg_path != path {
+                continue;
+            }
+            if app.p.flags.iter().any(|x| x.b.name == name) {
+                let key = match extension {
+                    ArgExtension {
+                        value_type: Some(_),
+                        ..
+                    } => "type",
+                    ArgExtension {
+                        complete_command: Some(_),
+                        ..
+                    } => "complete_command",
+                    ArgExtension {
+                        value_hint: Some(_),
+                        ..
+                    } => "value_hint",
+                    _ => continue,
+                };
+                bail!(
+                    "`{}` can't be used on argument '{}' because it doesn't take values",
+                    key,
+                    name
+                );
+            }
+            let value_type = match extension.value_type {
+                Some(x) => x,
+                None => continue,
+            };
+            let validator = Some(Rc::new(move |x: String| value_type.validate(&x))
+                as Rc<dyn Fn(String) -> Result<(), String>>);
+            for x in app.p.opts.iter_mut().filter(|x| x.b.name == name) {
+                x.v.validator = validator.clone();
+            }
+            for x in app.p.positionals.values_mut().filter(|x| x.b.name == name) {
+                x.v.validator = validator.clone();
+            }
+        }
+
+        for subcommand in app.p.subcommands.iter_mut() {
+            path.push(subcommand.get_name().to_owned());
+            self.apply(subcommand, path)?;
+            path.pop();
+        }
+
+        Ok(())
+    }
+}
+
+// The only entry of a map like `- name: {...}`, the way args and subcommands are declared.
+fn single_entry(yaml: &mut Yaml) -> Option<(String, &mut Yaml)> {
+    match yaml {
+        Yaml::Hash(x) if x.len() == 1 => {
+            let (k, v) = x.iter_mut().next().unwrap();
+            Some((k.as_str()?.to_owned(), v))
+        }
+        _ => None,
+    }
+}
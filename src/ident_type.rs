@@ -13,16 +13,34 @@ pub enum IdentType {
 
 impl IdentType {
     // Regex for validating the (head or tail) identifier.
-    pub fn re(&self, shell: &Shell) -> &'static Regex {
+    pub fn re<'a>(&self, shell: &'a Shell) -> &'a Regex {
         match self {
             Self::Head => match shell {
-                Shell::Bash | Shell::Zsh => &*CANNOT_START_WITH_NUM_RE,
-                Shell::Elvish | Shell::Fish => &*CAN_START_WITH_NUM_RE,
-                Shell::PowerShell => &*ANY_RE,
+                Shell::Bash
+                | Shell::Ksh
+                | Shell::Murex
+                | Shell::Rc
+                | Shell::Tcsh
+                | Shell::Xonsh
+                | Shell::Ysh
+                | Shell::Zsh => &CANNOT_START_WITH_NUM_RE,
+                Shell::Template(template) => template.ident(),
+                Shell::Elvish | Shell::Fish => &CAN_START_WITH_NUM_RE,
+                Shell::PowerShell => &ANY_RE,
             },
             Self::Tail => match shell {
-                Shell::Bash | Shell::Zsh | Shell::Elvish | Shell::Fish => &*CAN_START_WITH_NUM_RE,
-                Shell::PowerShell => &*ANY_RE,
+                Shell::Bash
+                | Shell::Elvish
+                | Shell::Fish
+                | Shell::Ksh
+                | Shell::Murex
+                | Shell::Rc
+                | Shell::Tcsh
+                | Shell::Template(_)
+                | Shell::Xonsh
+                | Shell::Ysh
+                | Shell::Zsh => &CAN_START_WITH_NUM_RE,
+                Shell::PowerShell => &ANY_RE,
             },
         }
     }
@@ -0,0 +1,68 @@
+//! Painless shell argument parsing and dependency check.
+//!
+//! This is the library behind the `slap` binary, it can be used to generate the shell code that
+//! `slap parse`, `slap completions` and `slap compile` output, without spawning slap.
+
+mod app_wrapper;
+mod compile;
+mod complete;
+mod config;
+mod config_checker;
+mod dependencies;
+mod error;
+mod extensions;
+mod ident_type;
+mod message_cache;
+mod script;
+mod shell;
+mod template;
+mod value_kind;
+mod var_names;
+
+pub use {
+    config::{Config, ExternalApp},
+    dependencies::Dependencies,
+    error::Error,
+    script::Script,
+    shell::{ParseOptions, Shell},
+    template::Template,
+    var_names::VarNames,
+};
+
+use std::ffi::OsString;
+
+/// Parses `args` (without the program name) using the YAML `config` and returns the code to
+/// evaluate in `shell`, like `slap parse`. Variables are prefixed with `var_prefix`.
+pub fn parse(
+    config: &str,
+    shell: &Shell,
+    args: &[OsString],
+    var_prefix: Option<&str>,
+) -> Result<String, Error> {
+    parse_with(config, shell, args, var_prefix, &ParseOptions::default())
+}
+
+/// Like `parse`, but the generated code can be changed with `options`.
+pub fn parse_with(
+    config: &str,
+    shell: &Shell,
+    args: &[OsString],
+    var_prefix: Option<&str>,
+    options: &ParseOptions,
+) -> Result<String, Error> {
+    Config::load(config)?
+        .app()?
+        .parse_with(shell, args, var_prefix, options)
+}
+
+/// Returns the completions script for `shell` of the CLI described by the YAML `config`, like
+/// `slap completions`.
+pub fn completions(config: &str, shell: &Shell) -> Result<String, Error> {
+    Config::load(config)?.app()?.completions(shell)
+}
+
+/// Returns a script for `shell` that parses its arguments like `parse` would with the YAML
+/// `config`, like `slap compile`.
+pub fn compile(config: &str, shell: &Shell, var_prefix: Option<&str>) -> Result<String, Error> {
+    Config::load(config)?.app()?.compile(shell, var_prefix)
+}
@@ -1,29 +1,122 @@
-mod app_wrapper;
-mod config_checker;
-mod dependencies;
-mod ident_type;
-mod shell;
-
-pub use {dependencies::Dependencies, shell::Shell};
+mod run;
+mod script_path;
+mod serve;
 
 use {
-    crate::app_wrapper::AppWrapper,
+    crate::script_path::Resolution,
     anyhow::{bail, Context},
-    clap::{App, AppSettings, Arg, ArgMatches, SubCommand, YamlLoader},
+    clap::{App, AppSettings, Arg, ArgMatches, SubCommand},
+    slap::{Config, Dependencies, Error, ExternalApp, ParseOptions, Shell, Template, VarNames},
     std::{
         convert::TryFrom,
-        env, fs,
+        env,
+        ffi::OsString,
+        fs,
         io::{self, Read},
-        path::Path,
-        process, str,
+        path::{Path, PathBuf},
+        process,
     },
-    yaml_rust::Yaml,
 };
 
+// The `parse` subcommand, also used to decode the requests of `slap serve`.
+fn parse_cli() -> App<'static, 'static> {
+    SubCommand::with_name("parse")
+        .about("Check the passed arguments and output code intended to be evaluated by your shell")
+        .arg(
+            Arg::with_name("SHELL")
+                .help("The target shell")
+                .index(1)
+                .required_unless("template")
+                .possible_values(&Shell::SHELLS),
+        )
+        .arg(
+            Arg::with_name("VAR_PREFIX")
+                .help("The prefix to use for the exported variables")
+                .index(2),
+        )
+        .arg(
+            Arg::with_name("template")
+                .help("Template file describing the target language, used instead of SHELL. VAR_PREFIX can't be given, use the `var-prefix` directive of the template instead")
+                .long("template")
+                .value_name("FILE")
+                .conflicts_with_all(&["SHELL", "VAR_PREFIX"])
+        )
+        .arg(
+            Arg::with_name("scalars")
+                .help("Emit single-valued arguments as scalars and flags as booleans, only multi-valued arguments as arrays")
+                .long("scalars")
+        )
+        .arg(
+            Arg::with_name("local")
+                .help("Declare the variables local to the function that evaluates the code")
+                .long("local")
+        )
+        .arg(
+            Arg::with_name("reset")
+                .help("Unset every variable that the YAML config can generate before assigning the new ones")
+                .long("reset")
+        )
+        .arg(
+            Arg::with_name("var_template")
+                .help("Template of the variable names, `{prefix}` is replaced with VAR_PREFIX, `{path}` with the subcommand path and `{arg}` with the argument name")
+                .long("var-template")
+                .value_name("TEMPLATE")
+                .default_value("{prefix}{path}{arg}")
+        )
+        .arg(
+            Arg::with_name("separator")
+                .help("Separator between the subcommands of the path and before the suffixes")
+                .long("separator")
+                .value_name("SEPARATOR")
+                .default_value("_")
+        )
+        .arg(
+            Arg::with_name("upper")
+                .help("Upper-case the variable names")
+                .long("upper")
+        )
+        .arg(
+            Arg::with_name("occurs_suffix")
+                .help("Suffix of the variables containing the occurrences of an argument")
+                .long("occurs-suffix")
+                .value_name("SUFFIX")
+                .default_value("occurs")
+        )
+        .arg(
+            Arg::with_name("indices_suffix")
+                .help("Suffix of the variables containing the indices of an argument")
+                .long("indices-suffix")
+                .value_name("SUFFIX")
+                .default_value("indices")
+        )
+        .arg(
+            Arg::with_name("vals_suffix")
+                .help("Suffix of the variables containing the values of an argument, can be empty")
+                .long("vals-suffix")
+                .value_name("SUFFIX")
+                .default_value("vals")
+        )
+        .arg(
+            Arg::with_name("vals_count_suffix")
+                .help("Suffix of the variables containing the number of values of an argument")
+                .long("vals-count-suffix")
+                .value_name("SUFFIX")
+                .default_value("vals_count")
+        )
+        .arg(
+            Arg::with_name("EXTERNAL_ARGS")
+                .help("Arguments to parse using the YAML config passed to STDIN")
+                .index(3)
+                .raw(true)
+                .allow_hyphen_values(true)
+                .multiple(true),
+        )
+}
+
 fn this_cli() -> ArgMatches<'static> {
     App::new("slap")
         .version(clap::crate_version!())
-        .author(clap::crate_authors!("\n"))
+        .author(env!("CARGO_PKG_AUTHORS"))
         .about(clap::crate_description!())
         .settings(&[
             AppSettings::ArgRequiredElseHelp,
@@ -44,29 +137,62 @@ fn this_cli() -> ArgMatches<'static> {
                         .possible_values(&Shell::SHELLS),
                 )
         )
+        .subcommand(parse_cli())
         .subcommand(
-            SubCommand::with_name("parse")
-                .about("Check the passed arguments and output code intended to be evaluated by your shell")
+            SubCommand::with_name("compile")
+                .about("Output a script that parses its arguments like `parse` does, for machines without slap")
                 .arg(
                     Arg::with_name("SHELL")
                         .help("The target shell")
                         .index(1)
                         .required(true)
-                        .possible_values(&Shell::SHELLS),
+                        .possible_values(&["bash"]),
                 )
                 .arg(
                     Arg::with_name("VAR_PREFIX")
                         .help("The prefix to use for the exported variables")
                         .index(2),
                 )
+        )
+        .subcommand(
+            SubCommand::with_name("complete")
+                .about("Output the candidates of the value being completed, called by the completions scripts")
+                .setting(AppSettings::Hidden)
                 .arg(
-                    Arg::with_name("EXTERNAL_ARGS")
-                        .help("Arguments to parse using the YAML config passed to STDIN")
-                        .index(3)
+                    Arg::with_name("WORDS")
+                        .help("The command line after the program name, the last word is being completed")
+                        .index(1)
                         .raw(true)
                         .allow_hyphen_values(true)
                         .multiple(true),
-                ),
+                )
+        )
+        .subcommand(
+            SubCommand::with_name("run")
+                .about("Run a script that declares its YAML config and shell in a front matter, to be used in shebangs")
+                .arg(
+                    Arg::with_name("SCRIPT")
+                        .help("The script, its YAML config is between two `---` lines after the shebang")
+                        .index(1)
+                        .required(true)
+                )
+                .arg(
+                    Arg::with_name("ARGS")
+                        .help("Arguments of the script")
+                        .index(2)
+                        .multiple(true)
+                )
+        )
+        .subcommand(
+            SubCommand::with_name("serve")
+                .about("Answer parse requests coming from STDIN, to be used as a co-process by a client shim")
+                .arg(
+                    Arg::with_name("client")
+                        .help("Output the client shim for the specified shell instead")
+                        .long("client")
+                        .value_name("SHELL")
+                        .possible_values(&serve::CLIENT_SHELLS)
+                )
         )
         .subcommand(
             SubCommand::with_name("deps")
@@ -83,21 +209,22 @@ fn this_cli() -> ArgMatches<'static> {
                         .help("Lists every dependency not found in $PATH")
                         .long("failed")
                         .short("f")
-                        .conflicts_with_all(&["succeded", "all"])
+                        .conflicts_with_all(&["succeeded", "all"])
                 )
                 .arg(
-                    Arg::with_name("succeded")
+                    Arg::with_name("succeeded")
                         .help("Lists the absolute path of every dependency found in $PATH")
-                        .long("succeded")
+                        .long("succeeded")
+                        .alias("succeded")
                         .short("s")
                         .conflicts_with_all(&["failed", "all"])
                 )
                 .arg(
                     Arg::with_name("all")
-                        .help("Outputs a JSON containing succeded and failed dependencies (can easily be parsed using jq)")
+                        .help("Outputs a JSON containing succeeded and failed dependencies (can easily be parsed using jq)")
                         .long("all")
                         .short("a")
-                        .conflicts_with_all(&["failed", "succeded"])
+                        .conflicts_with_all(&["failed", "succeeded"])
                 )
                 .arg(
                     Arg::with_name("pretty")
@@ -105,6 +232,24 @@ fn this_cli() -> ArgMatches<'static> {
                         .long("pretty")
                         .short("p")
                         .requires("all")
+                )
+                .arg(
+                    Arg::with_name("path")
+                        .help("Search dependencies in this path list (same format of $PATH) instead of $PATH")
+                        .long("path")
+                        .value_name("PATHLIST")
+                        .takes_value(true)
+                )
+                .arg(
+                    Arg::with_name("include_cwd")
+                        .help("Search dependencies in the current directory before the other paths")
+                        .long("include-cwd")
+                )
+                .arg(
+                    Arg::with_name("every")
+                        .help("Report every match of a dependency, including the ones shadowed by the first match")
+                        .long("every")
+                        .short("e")
                 ),
         )
         .subcommand(
@@ -120,16 +265,44 @@ fn this_cli() -> ArgMatches<'static> {
                     Arg::with_name("dir_only")
                         .long("dir-only")
                         .short("d")
-                        .conflicts_with("dereference")
                         .help("Gives you the absolute path of the script without including the script name")
                 )
                 .arg(
                     Arg::with_name("dereference")
                         .long("dereference")
                         .short("D")
-                        .conflicts_with("dir_only")
+                        .conflicts_with("canonicalize")
                         .help("If the path points to a symlink, the dereferenced path will be printed")
                 )
+                .arg(
+                    Arg::with_name("canonicalize")
+                        .long("canonicalize")
+                        .short("c")
+                        .conflicts_with("dereference")
+                        .help("Follows every symlink (chains included) and prints the final absolute path")
+                )
+                .arg(
+                    Arg::with_name("logical")
+                        .long("logical")
+                        .short("L")
+                        .overrides_with("physical")
+                        .help("Keeps symlinks in the directory of the script, using $PWD like `pwd -L`")
+                )
+                .arg(
+                    Arg::with_name("physical")
+                        .long("physical")
+                        .short("P")
+                        .overrides_with("logical")
+                        .help("Resolves symlinks in the directory of the script, like `pwd -P` (default)")
+                )
+                .arg(
+                    Arg::with_name("relative_to")
+                        .long("relative-to")
+                        .short("r")
+                        .value_name("DIR")
+                        .takes_value(true)
+                        .help("Prints the path relative to DIR instead of the absolute path")
+                )
         )
         .get_matches()
 }
@@ -137,121 +310,220 @@ fn this_cli() -> ArgMatches<'static> {
 fn path_subcmd(matches: &ArgMatches) -> anyhow::Result<()> {
     let relativep = matches.value_of("SCRIPT_RELATIVE_PATH").unwrap();
     let relativep = Path::new(relativep);
-    let script_name = relativep
-        .file_name()
-        .with_context(|| format!("Can't get file name of path '{}'", relativep.display()))?;
-
-    let dirname = relativep
-        .parent()
-        .context("Can't get parent path of root (/)")?;
-    env::set_current_dir(dirname)
-        .with_context(|| format!("Failed to cd in '{}'", dirname.display()))?;
-    let mut current_dir = env::current_dir()?;
-    if !matches.is_present("dir_only") {
-        current_dir = current_dir.join(script_name);
+    if relativep.file_name().is_none() {
+        bail!("Can't get file name of path '{}'", relativep.display());
     }
 
+    let resolution = if matches.is_present("logical") {
+        Resolution::Logical
+    } else {
+        Resolution::Physical
+    };
+    let relative_to = match matches.value_of("relative_to") {
+        Some(x) => Some(
+            resolution
+                .directory(Path::new(x))
+                .with_context(|| format!("Failed to resolve '{}'", x))?,
+        ),
+        None => None,
+    };
+    let mut abs = resolution
+        .absolute(relativep)
+        .with_context(|| format!("Failed to resolve '{}'", relativep.display()))?;
+
     if matches.is_present("dereference") {
-        if let Ok(dereferencedp) = fs::read_link(&current_dir) {
-            current_dir = dereferencedp;
+        let dereferencedp = script_path::dereference(&abs);
+        abs = resolution
+            .absolute(&dereferencedp)
+            .with_context(|| format!("Failed to resolve '{}'", dereferencedp.display()))?;
+    }
+    if matches.is_present("canonicalize") {
+        abs = script_path::canonicalize(&abs)
+            .with_context(|| format!("Failed to canonicalize '{}'", abs.display()))?;
+    }
+    if matches.is_present("dir_only") {
+        if let Some(parent) = abs.parent() {
+            abs = parent.to_path_buf();
         }
     }
+    if let Some(mut relative_to) = relative_to {
+        // DIR is resolved like the path, so their common components are found.
+        if matches.is_present("canonicalize") {
+            relative_to = script_path::canonicalize(&relative_to)
+                .with_context(|| format!("Failed to canonicalize '{}'", relative_to.display()))?;
+        }
+        abs = script_path::relative_to(&abs, &relative_to);
+    }
 
-    println!("{}", current_dir.display());
+    println!("{}", abs.display());
 
     Ok(())
 }
 
-// FIXME: Fix ZSH not generating the code for completion.
 fn autocompletions_subcmd(
     matches: &ArgMatches,
-    external_app: &mut AppWrapper,
-    name: &str,
+    external_app: &mut ExternalApp,
 ) -> anyhow::Result<()> {
     let shell = Shell::try_from(matches.value_of("SHELL").unwrap()).unwrap();
-    let completions_script = external_app.completions_script(name, &shell)?;
+    let completions_script = external_app.completions(&shell)?;
     println!("{}", completions_script);
     Ok(())
 }
 
-fn parse_subcmd(
-    matches: &ArgMatches,
-    name: &str,
-    external_app: AppWrapper,
-    external_app_subcommands: &[AppWrapper],
-    help_msg: &str,
-    version_msg: &str,
-) -> anyhow::Result<()> {
+fn compile_subcmd(matches: &ArgMatches, external_app: &ExternalApp) -> anyhow::Result<()> {
     let shell = Shell::try_from(matches.value_of("SHELL").unwrap()).unwrap();
-    let mut external_args = matches
-        .values_of("EXTERNAL_ARGS")
-        .map(|x| x.collect::<Vec<_>>())
-        .unwrap_or_default();
-    let var_prefix = matches.value_of("VAR_PREFIX");
-
-    external_args.insert(0, &name);
-    let external_matches = external_app.app.get_matches_from(external_args);
-
-    // We can't output help or version messages to stdout. Only to stderr.
-    // The only thing that we can output to stdout is the code that the user will eval.
-    if let Some(ref subcmd) = external_matches.subcommand {
-        let subcmd_matches = &subcmd.matches;
-        let subcmd_name = &subcmd.name;
+    let script = external_app.compile(&shell, matches.value_of("VAR_PREFIX"))?;
+    println!("{}", script);
+    Ok(())
+}
 
-        macro_rules! handle_subcmd {
-            ( $x:ident, $y:ident ) => {
-                let subcmd = external_app_subcommands
-                    .into_iter()
-                    .find(|x| x.app.get_name() == $y)
-                    .unwrap();
-                eprintln!("{}", subcmd.$x);
-                return Ok(());
-            };
+// Prints `values` followed by the candidates printed by the `complete_command` of the argument
+// of the value being completed, that start with the word being completed. Exits with 1 if there
+// is no `complete_command`, or if it fails, so the completions scripts fall back to their static
+// completions.
+fn complete_subcmd(matches: &ArgMatches, external_app: &ExternalApp) -> anyhow::Result<()> {
+    // Words that aren't valid UTF-8 can't match the names of the config, but they can be
+    // completed.
+    let words = matches
+        .values_of_os("WORDS")
+        .map(|x| {
+            x.map(|x| x.to_string_lossy().into_owned())
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    let command = match external_app.complete_command(&words) {
+        Some(x) => x,
+        None => process::exit(1),
+    };
+    let output = process::Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .stderr(process::Stdio::null())
+        .output()
+        .with_context(|| format!("Failed to run `{}`", command))?;
+    if !output.status.success() {
+        process::exit(1);
+    }
+    let current = ExternalApp::value_prefix(&words);
+    println!("values");
+    for candidate in String::from_utf8_lossy(&output.stdout).lines() {
+        if candidate.starts_with(current) {
+            println!("{}", candidate);
         }
+    }
+    Ok(())
+}
 
-        if subcmd_name == "help" {
-            if subcmd_matches.is_present("help") {
-                eprintln!("{}", help_msg);
-                return Ok(());
-            }
-            if subcmd_matches.is_present("version") {
-                eprintln!("{}", version_msg);
-                return Ok(());
+// The arguments of the `parse` subcommand.
+struct ParseRequest<'a> {
+    shell: Shell,
+    external_args: Vec<OsString>,
+    var_prefix: Option<&'a str>,
+    options: ParseOptions,
+}
+
+impl<'a> ParseRequest<'a> {
+    fn from_matches(matches: &'a ArgMatches) -> anyhow::Result<Self> {
+        let shell = match matches.value_of("template") {
+            Some(path) => {
+                let template = fs::read_to_string(path)
+                    .with_context(|| format!("Failed to read the template '{}'", path))?;
+                let template = Template::load(&template)
+                    .with_context(|| format!("Invalid template '{}'", path))?;
+                Shell::Template(Box::new(template))
             }
-            match subcmd_matches.value_of("SUBCMD") {
-                Some(help_subcmd) => {
-                    handle_subcmd!(help_msg, help_subcmd);
-                }
-                None => {
-                    eprintln!("{}", external_app.help_msg);
-                    return Ok(());
+            None => Shell::try_from(matches.value_of("SHELL").unwrap()).unwrap(),
+        };
+        let external_args = matches
+            .values_of_os("EXTERNAL_ARGS")
+            .map(|x| x.map(OsString::from).collect::<Vec<_>>())
+            .unwrap_or_default();
+        let var_prefix = matches.value_of("VAR_PREFIX");
+        let var_names = {
+            let mut var_names = VarNames::default();
+            let fields = [
+                ("var_template", &mut var_names.template),
+                ("separator", &mut var_names.separator),
+                ("occurs_suffix", &mut var_names.occurs_suffix),
+                ("indices_suffix", &mut var_names.indices_suffix),
+                ("vals_suffix", &mut var_names.vals_suffix),
+                ("vals_count_suffix", &mut var_names.vals_count_suffix),
+            ];
+            for (name, field) in fields {
+                if let Some(x) = matches.value_of(name) {
+                    *field = x.into();
                 }
             }
-        }
-        if subcmd_matches.is_present("help") {
-            handle_subcmd!(help_msg, subcmd_name);
-        }
-        if subcmd_matches.is_present("version") {
-            handle_subcmd!(version_msg, subcmd_name);
-        }
-    } else {
-        if external_matches.is_present("help") {
-            eprintln!("{}", external_app.help_msg);
-            return Ok(());
-        }
-        if external_matches.is_present("version") {
-            eprintln!("{}", external_app.version_msg);
-            return Ok(());
-        }
+            var_names.upper = matches.is_present("upper");
+            var_names
+        };
+        let options = ParseOptions {
+            scalars: matches.is_present("scalars"),
+            var_names,
+            local: matches.is_present("local"),
+            reset: matches.is_present("reset"),
+        };
+        Ok(Self {
+            shell,
+            external_args,
+            var_prefix,
+            options,
+        })
     }
+}
 
-    let code = shell.parse(external_matches, var_prefix)?;
-    println!("{}", code);
+fn parse_subcmd(matches: &ArgMatches, external_app: &ExternalApp) -> anyhow::Result<()> {
+    let ParseRequest {
+        shell,
+        external_args,
+        var_prefix,
+        options,
+    } = ParseRequest::from_matches(matches)?;
+
+    match external_app.parse_with(&shell, &external_args, var_prefix, &options) {
+        Ok(code) => println!("{}", code),
+        // We can't output help or version messages to stdout. Only to stderr.
+        // The only thing that we can output to stdout is the code that the user will eval.
+        Err(Error::Help(msg)) | Err(Error::Version(msg)) => eprintln!("{}", msg),
+        Err(Error::Args(e)) => e.exit(),
+        Err(e) => return Err(e.into()),
+    }
 
     Ok(())
 }
 
+// `$XDG_CACHE_HOME/slap`, or `~/.cache/slap`. There's no cache if `$SLAP_NO_CACHE` isn't empty.
+fn cache_dir() -> Option<PathBuf> {
+    if env::var_os("SLAP_NO_CACHE").is_some_and(|x| !x.is_empty()) {
+        return None;
+    }
+    env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .filter(|x| x.is_absolute())
+        .or_else(|| env::var_os("HOME").map(|x| Path::new(&x).join(".cache")))
+        .map(|x| x.join("slap"))
+}
+
+fn message_cache(config: Config) -> Config {
+    match cache_dir() {
+        Some(dir) => config.with_message_cache(&dir),
+        None => config,
+    }
+}
+
+fn load_config(s: &str) -> Result<Config, Error> {
+    match cache_dir() {
+        Some(dir) => Config::load_cached(s, &dir),
+        None => Config::load(s),
+    }
+}
+
 fn main() -> anyhow::Result<()> {
+    let args = env::args_os().collect::<Vec<_>>();
+    if let Some(args) = run::script_args(&args) {
+        return run::run_subcmd(args);
+    }
+
     let matches = this_cli();
 
     match Dependencies::check(&matches) {
@@ -264,91 +536,32 @@ fn main() -> anyhow::Result<()> {
         return path_subcmd(matches);
     }
 
+    if let Some(matches) = matches.subcommand_matches("serve") {
+        return serve::serve_subcmd(matches);
+    }
+
     let stdin = {
         let mut stdin = String::new();
         io::stdin().read_to_string(&mut stdin)?;
-        if stdin.is_empty() {
-            bail!("Received an empty string from STDIN. Check that the YAML config file exists")
-        }
         stdin
     };
+    let config = load_config(&stdin)?;
+    let mut external_app = config.app()?;
 
-    let yaml_loader = {
-        let mut yaml_loader = YamlLoader::load_from_str(&stdin)?;
-        yaml_loader.remove(0)
-    };
-    let yaml_config = yaml_loader.into_hash().context("Invalid YAML config")?;
-    config_checker::required(&yaml_config)?;
-    config_checker::banned(&yaml_config)?;
-
-    let subcommands_key = YamlLoader::load_from_str("subcommands")
-        .ok()
-        .map(|mut x| x.remove(0))
-        .unwrap();
-
-    // Clap doesn't let us redirect --help and --version to stderr so we have to do it manually.
-    // This block of code parses the subcommands into a Vec<AppWrapper> and removes from the
-    // YamlLoader the subcommands parts so we can add them manually later to the `external_app`
-    // clap::App.
-    let external_app_subcommands = {
-        if yaml_config.contains_key(&subcommands_key) {
-            let subcommands = yaml_config.get(&subcommands_key).unwrap();
-            let external_app_subcommands = subcommands
-                .as_vec()
-                .context("Subcommands object must be an array of maps")?
-                .iter()
-                .map(SubCommand::from_yaml)
-                .map(|x| AppWrapper::new(x, |app| app));
-            let mut xs = Vec::new();
-            for subcmd in external_app_subcommands {
-                xs.push(subcmd?);
-            }
-            xs
-        } else {
-            Default::default()
-        }
-    };
-
-    let yaml_loader = {
-        #[allow(clippy::redundant_clone)]
-        let mut yaml_config = yaml_config.clone();
-        yaml_config.remove_entry(&subcommands_key);
-        let new_yaml_content = {
-            let mut buffer = String::new();
-            let mut emitter = yaml_rust::YamlEmitter::new(&mut buffer);
-            let yaml_hash = Yaml::Hash(yaml_config);
-            emitter.dump(&yaml_hash).unwrap();
-            buffer
-        };
-        let mut loader = YamlLoader::load_from_str(&new_yaml_content)?;
-        loader.remove(0)
-    };
+    if let Some(matches) = matches.subcommand_matches("completions") {
+        return autocompletions_subcmd(matches, &mut external_app);
+    }
 
-    let external_app_help_subcmd = AppWrapper::new(SubCommand::with_name("help"), |app: App| {
-        app.arg(Arg::with_name("SUBCMD").required(false))
-            .about("Prints this message or the help of the given subcommand(s)")
-    })?;
-    let external_app = App::from(&yaml_loader);
-    let name = external_app.get_name().to_owned();
-    let mut external_app = AppWrapper::new(external_app.bin_name(&name), {
-        let subcommand = external_app_help_subcmd.app;
-        let subcommands = external_app_subcommands.clone().into_iter().map(|x| x.app);
-        move |app: App| app.subcommand(subcommand).subcommands(subcommands)
-    })?;
+    if let Some(matches) = matches.subcommand_matches("parse") {
+        return parse_subcmd(matches, &external_app);
+    }
 
-    if matches.subcommand_matches("completions").is_some() {
-        return autocompletions_subcmd(&matches, &mut external_app, &name);
+    if let Some(matches) = matches.subcommand_matches("complete") {
+        return complete_subcmd(matches, &external_app);
     }
 
-    if let Some(matches) = matches.subcommand_matches("parse") {
-        return parse_subcmd(
-            matches,
-            &name,
-            external_app,
-            &external_app_subcommands,
-            &external_app_help_subcmd.help_msg,
-            &external_app_help_subcmd.version_msg,
-        );
+    if let Some(matches) = matches.subcommand_matches("compile") {
+        return compile_subcmd(matches, &external_app);
     }
 
     Ok(())
@@ -0,0 +1,106 @@
+use {
+    serde_json::{Map, Value},
+    std::{
+        fs, io,
+        path::{Path, PathBuf},
+        process,
+    },
+};
+
+// FNV-1a, unlike the hasher of the standard library it's stable between runs and versions.
+pub(crate) fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, x| {
+        (hash ^ u64::from(*x)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+// How many configs are cached, the files of the others are deleted starting from the oldest.
+const MAX_FILES: usize = 64;
+
+// What is computed from a config and can be reused by later runs: the help and version
+// messages of its apps, that are slow to render for configs with many subcommands, and whether
+// it passed the checks of `Config::load`. The file is keyed by the hash of the YAML config and
+// of the version of slap, so a changed config or a new slap never reads stale entries.
+pub struct MessageCache {
+    path: PathBuf,
+}
+
+impl MessageCache {
+    pub fn new(dir: &Path, yaml: &str) -> Self {
+        let key = [env!("CARGO_PKG_VERSION").as_bytes(), b"\0", yaml.as_bytes()].concat();
+        Self {
+            path: dir.join(format!("{:016x}.json", fnv1a(&key))),
+        }
+    }
+
+    // The cache is best effort, failing to read or write it isn't an error.
+    fn entries(&self) -> Map<String, Value> {
+        fs::read(&self.path)
+            .ok()
+            .and_then(|x| serde_json::from_slice::<Map<String, Value>>(&x).ok())
+            .unwrap_or_default()
+    }
+
+    // The message `kind` ("help" or "version") of the app `app`, rendered and stored if it
+    // isn't cached yet.
+    pub fn get_or_render(
+        &self,
+        app: &str,
+        kind: &str,
+        render: impl FnOnce() -> anyhow::Result<String>,
+    ) -> anyhow::Result<String> {
+        let mut entries = self.entries();
+        let key = format!("{} {}", kind, app);
+        if let Some(Value::String(msg)) = entries.get(&key) {
+            return Ok(msg.clone());
+        }
+
+        let msg = render()?;
+        entries.insert(key, msg.clone().into());
+        let _ = self.write(entries);
+        Ok(msg)
+    }
+
+    // Whether the config passed the checks in an earlier run.
+    pub fn is_checked(&self) -> bool {
+        self.entries().get("checked") == Some(&Value::Bool(true))
+    }
+
+    pub fn set_checked(&self) {
+        let mut entries = self.entries();
+        entries.insert("checked".into(), true.into());
+        let _ = self.write(entries);
+    }
+
+    // Written to a temporary file first, so concurrent runs never read a partial file.
+    fn write(&self, entries: Map<String, Value>) -> io::Result<()> {
+        let dir = match self.path.parent() {
+            Some(x) => x,
+            None => return Ok(()),
+        };
+        fs::create_dir_all(dir)?;
+        let tmp = self.path.with_extension(format!("{}.tmp", process::id()));
+        fs::write(&tmp, Value::Object(entries).to_string())?;
+        fs::rename(&tmp, &self.path)?;
+        prune(dir)
+    }
+}
+
+// Deletes the least recently written files of `dir` beyond `MAX_FILES`.
+fn prune(dir: &Path) -> io::Result<()> {
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.extension().is_some_and(|x| x == "json") {
+            let modified = fs::metadata(&path).and_then(|x| x.modified());
+            files.push((modified.ok(), path));
+        }
+    }
+    if files.len() > MAX_FILES {
+        files.sort();
+        for (_, path) in &files[..files.len() - MAX_FILES] {
+            let _ = fs::remove_file(path);
+        }
+    }
+    Ok(())
+}
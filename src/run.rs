@@ -0,0 +1,121 @@
+use {
+    crate::message_cache,
+    anyhow::{bail, Context},
+    slap::{Error, Script, Shell},
+    std::{ffi::OsString, fs, path::Path, process::Command},
+};
+
+// A link to slap with this name runs its first argument like `slap run`, because Linux passes
+// `slap run` of `#!/usr/bin/env slap run` to env as a single argument.
+const INTERPRETER: &str = "slap-run";
+
+// The script and its arguments if slap was started as an interpreter, by `slap-run SCRIPT ...`
+// or `slap run SCRIPT ...`. They aren't parsed by clap, that would take the options of the
+// script and `--`. Without a script `slap run` is left to clap, that prints its help: the `run`
+// subcommand of clap is only reached for `slap run --help` and the errors of `slap run`.
+pub fn script_args(args: &[OsString]) -> Option<&[OsString]> {
+    let is_interpreter = args
+        .first()
+        .and_then(|x| Path::new(x).file_name())
+        .is_some_and(|x| x == INTERPRETER);
+    let args = if is_interpreter {
+        &args[1..]
+    } else if args.get(1).is_some_and(|x| x == "run") {
+        &args[2..]
+    } else {
+        return None;
+    };
+    match args.first() {
+        Some(x) if !x.to_string_lossy().starts_with('-') => Some(args),
+        _ if is_interpreter => Some(args),
+        _ => None,
+    }
+}
+
+// The program that runs the scripts for `shell`, and the code it runs with `-c`: it evaluates the
+// parsed arguments of `$__slap_run_code`, then the script. Linux rejects arguments longer than
+// 128 KiB, so the shell reads the script from its file, `$0` or `$__slap_run_script` for fish,
+// with the first `front_matter_lines` lines left blank so `$LINENO` is the line of the file.
+fn interpreter(shell: &Shell, front_matter_lines: usize) -> anyhow::Result<(&'static str, String)> {
+    let (n, m) = (front_matter_lines, front_matter_lines + 1);
+    let posix = format!(
+        "eval \"$__slap_run_code\"; unset __slap_run_code; \
+         eval \"$(i=0; while [ $i -lt {} ]; do echo; i=$((i + 1)); done; tail -n +{} -- \"$0\")\"",
+        n, m
+    );
+    Ok(match shell {
+        Shell::Bash => ("bash", posix),
+        Shell::Ksh => ("ksh", posix),
+        Shell::Zsh => ("zsh", posix),
+        // The arguments after the code are `$argv`.
+        Shell::Fish => (
+            "fish",
+            format!(
+                "eval $__slap_run_code; set -e __slap_run_code; \
+                 eval (begin; for i in (seq {}); echo; end; tail -n +{} -- $__slap_run_script; end \
+                 | string collect; set -e __slap_run_script)",
+                n, m
+            ),
+        ),
+        _ => bail!("`slap run` can only run bash, fish, ksh and zsh scripts"),
+    })
+}
+
+#[cfg(unix)]
+fn exec(mut command: Command) -> std::io::Error {
+    use std::os::unix::process::CommandExt;
+    command.exec()
+}
+
+#[cfg(not(unix))]
+fn exec(mut command: Command) -> std::io::Error {
+    match command.status() {
+        Ok(status) => std::process::exit(status.code().unwrap_or(1)),
+        Err(e) => e,
+    }
+}
+
+pub fn run_subcmd(args: &[OsString]) -> anyhow::Result<()> {
+    let path = match args.first() {
+        Some(x) => Path::new(x),
+        None => bail!("Usage: {} SCRIPT [ARGS]...", INTERPRETER),
+    };
+    let source = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read the script '{}'", path.display()))?;
+    let Script {
+        config,
+        shell,
+        var_prefix,
+        front_matter_lines,
+        ..
+    } = Script::load(&source).with_context(|| format!("Invalid script '{}'", path.display()))?;
+    let (program, bootstrap) = interpreter(&shell, front_matter_lines)?;
+
+    let config = message_cache(config);
+    let code = match config
+        .app()?
+        .parse(&shell, &args[1..], var_prefix.as_deref())
+    {
+        Ok(code) => code,
+        // The script is the program that was run, so its messages go to stdout.
+        Err(Error::Help(msg)) | Err(Error::Version(msg)) => {
+            println!("{}", msg);
+            return Ok(());
+        }
+        Err(Error::Args(e)) => e.exit(),
+        Err(e) => return Err(e.into()),
+    };
+
+    let mut command = Command::new(program);
+    command
+        .env("__slap_run_code", code)
+        .arg("-c")
+        .arg(bootstrap);
+    if let Shell::Fish = shell {
+        command.env("__slap_run_script", path);
+    } else {
+        command.arg(path);
+    }
+    command.args(&args[1..]);
+    Err(exec(command)).with_context(|| format!("Failed to run `{}`", program))
+}
@@ -0,0 +1,83 @@
+use {
+    crate::{Config, Error, Shell},
+    anyhow::anyhow,
+    std::convert::TryFrom,
+    yaml_rust::Yaml,
+};
+
+/// A script run by `slap run`. After the shebang, its YAML config is written between two `---`
+/// lines, with the `shell` that runs the rest of the file and an optional `var_prefix`.
+pub struct Script {
+    /// The YAML config of the front matter, without `shell` and `var_prefix`.
+    pub config: Config,
+    /// The shell that runs the body and that the code of the parsed arguments is generated for.
+    pub shell: Shell,
+    /// The prefix of the variables, like the VAR_PREFIX of `slap parse`.
+    pub var_prefix: Option<String>,
+    /// The code after the front matter.
+    pub body: String,
+    /// The number of lines before the body: the shebang and the front matter with its `---`.
+    pub front_matter_lines: usize,
+}
+
+// Whether `line` is the delimiter of the front matter.
+fn is_delimiter(line: &str) -> bool {
+    line.trim_end() == "---"
+}
+
+impl Script {
+    /// Splits the script `s` in its front matter, loaded as a YAML config, and its body.
+    pub fn load(s: &str) -> Result<Self, Error> {
+        let mut lines = s.split_inclusive('\n').peekable();
+        if lines.peek().is_some_and(|x| x.starts_with("#!")) {
+            lines.next();
+        }
+        if !lines.next().is_some_and(is_delimiter) {
+            return Err(Error::Config(anyhow!(
+                "The script must start with its YAML config between two `---` lines, after the shebang"
+            )));
+        }
+        let mut front_matter = String::new();
+        loop {
+            match lines.next() {
+                Some(line) if is_delimiter(line) => break,
+                Some(line) => front_matter.push_str(line),
+                None => {
+                    return Err(Error::Config(anyhow!(
+                        "The YAML config of the script must be followed by a `---` line"
+                    )))
+                }
+            }
+        }
+        let body = lines.collect::<String>();
+        let front_matter_lines = s[..s.len() - body.len()].matches('\n').count();
+
+        let mut yaml = Config::load_yaml(&front_matter)?;
+        let mut take = |key: &str| match yaml {
+            Yaml::Hash(ref mut hash) => hash.remove(&Yaml::String(key.into())),
+            _ => None,
+        };
+        let shell = match take("shell") {
+            Some(Yaml::String(x)) => Shell::try_from(x.as_str()).map_err(Error::Config)?,
+            Some(_) => return Err(Error::Config(anyhow!("`shell` must be a string"))),
+            None => {
+                return Err(Error::Config(anyhow!(
+                    "The YAML config of the script must contain an entry named 'shell'"
+                )))
+            }
+        };
+        let var_prefix = match take("var_prefix") {
+            Some(Yaml::String(x)) => Some(x),
+            Some(_) => return Err(Error::Config(anyhow!("`var_prefix` must be a string"))),
+            None => None,
+        };
+
+        Ok(Self {
+            config: Config::from_yaml(yaml, front_matter, None)?,
+            shell,
+            var_prefix,
+            body,
+            front_matter_lines,
+        })
+    }
+}
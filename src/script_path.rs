@@ -0,0 +1,112 @@
+use std::{
+    env, fs, io,
+    path::{Component, Path, PathBuf},
+};
+
+// How symlinks in the directory components of a path are treated, like `pwd -L` and `pwd -P`.
+#[derive(Clone, Copy)]
+pub enum Resolution {
+    // Symlinks are kept, `..` removes the previous component lexically.
+    Logical,
+    // Symlinks are resolved.
+    Physical,
+}
+
+impl Resolution {
+    // The current directory, taken from `$PWD` in logical mode if it's valid.
+    pub fn current_dir(self) -> io::Result<PathBuf> {
+        let physical = env::current_dir()?;
+        if let Self::Logical = self {
+            if let Some(pwd) = env::var_os("PWD").map(PathBuf::from) {
+                // Same checks done by `pwd -L`: `$PWD` must be absolute, without `.` or `..`
+                // components and must point to the current directory.
+                let is_valid = pwd.is_absolute()
+                    && pwd
+                        .components()
+                        .all(|x| !matches!(x, Component::CurDir | Component::ParentDir))
+                    && fs::canonicalize(&pwd).ok().as_ref() == Some(&physical);
+                if is_valid {
+                    return Ok(pwd);
+                }
+            }
+        }
+        Ok(physical)
+    }
+
+    // Makes the directory `path` absolute, resolving every symlink in physical mode like `cd -P`.
+    pub fn directory(self, path: &Path) -> io::Result<PathBuf> {
+        let path = self.current_dir()?.join(path);
+        match self {
+            Self::Logical => Ok(normalize(&path)),
+            Self::Physical => fs::canonicalize(path),
+        }
+    }
+
+    // Makes `path` absolute without touching the process working directory. The last component
+    // is never resolved, even in physical mode, so a path to a symlink stays a path to a symlink.
+    // In physical mode the parent is resolved before its `..` are applied, like `cd -P`.
+    pub fn absolute(self, path: &Path) -> io::Result<PathBuf> {
+        match (path.parent(), path.file_name()) {
+            (Some(parent), Some(file_name)) => Ok(self.directory(parent)?.join(file_name)),
+            _ => self.directory(path),
+        }
+    }
+}
+
+// Removes `.` and `..` components lexically, `..` at the root stays at the root.
+pub fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for x in path.components() {
+        match x {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if !normalized.pop() {
+                    normalized.push(x);
+                }
+            }
+            _ => normalized.push(x),
+        }
+    }
+    normalized
+}
+
+// Reads the symlink at `path` once, making the target absolute if the symlink is relative.
+// Returns `path` itself if it isn't a symlink.
+pub fn dereference(path: &Path) -> PathBuf {
+    match fs::read_link(path) {
+        Ok(target) if target.is_relative() => match path.parent() {
+            Some(parent) => parent.join(target),
+            None => target,
+        },
+        Ok(target) => target,
+        Err(_) => path.to_path_buf(),
+    }
+}
+
+// Follows every symlink of `path` (chains included) until the final absolute path.
+pub fn canonicalize(path: &Path) -> io::Result<PathBuf> {
+    fs::canonicalize(path)
+}
+
+// Expresses the absolute path `path` relative to the absolute path `base`.
+pub fn relative_to(path: &Path, base: &Path) -> PathBuf {
+    let path = path.components().collect::<Vec<_>>();
+    let base = base.components().collect::<Vec<_>>();
+    let common = path
+        .iter()
+        .zip(base.iter())
+        .take_while(|(x, y)| x == y)
+        .count();
+
+    let mut relativep = PathBuf::new();
+    for _ in &base[common..] {
+        relativep.push(Component::ParentDir);
+    }
+    for x in &path[common..] {
+        relativep.push(x);
+    }
+    if relativep.as_os_str().is_empty() {
+        relativep.push(Component::CurDir);
+    }
+    relativep
+}
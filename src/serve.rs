@@ -0,0 +1,199 @@
+use {
+    crate::{load_config, parse_cli, ParseRequest},
+    anyhow::Context,
+    clap::{AppSettings, ArgMatches, ErrorKind},
+    slap::{Config, Error, ExternalApp},
+    std::{
+        collections::VecDeque,
+        env,
+        ffi::OsString,
+        io::{self, BufRead, Write},
+    },
+};
+
+pub const CLIENT_SHELLS: [&str; 2] = ["bash", "zsh"];
+
+// Every field of requests and responses is terminated by a NUL byte.
+//
+// A request is the number of arguments, the YAML config and the arguments of `slap parse`.
+// A response is the exit code, the code to evaluate and the message to print to stderr.
+const BASH_CLIENT: &str = r#"# Usage: slap_parse CONFIG_FILE SLAP_PARSE_ARGS... && eval "$slap_code"
+slap_parse() {
+    local config ret err
+    config="$(<"$1")" || return 1
+    shift
+    if [[ -z "${SLAP_SERVE_PID-}" ]]; then
+        coproc SLAP_SERVE { {SLAP} serve; }
+    fi
+    printf '%s\0' "$#" "$config" "$@" >&"${SLAP_SERVE[1]}"
+    IFS= read -r -d '' ret <&"${SLAP_SERVE[0]}" || return 1
+    IFS= read -r -d '' slap_code <&"${SLAP_SERVE[0]}" || return 1
+    IFS= read -r -d '' err <&"${SLAP_SERVE[0]}" || return 1
+    [[ -n "$err" ]] && printf '%s\n' "$err" >&2
+    return "$ret"
+}"#;
+
+const ZSH_CLIENT: &str = r#"# Usage: slap_parse CONFIG_FILE SLAP_PARSE_ARGS... && eval "$slap_code"
+slap_parse() {
+    local config ret err
+    config="$(<"$1")" || return 1
+    shift
+    if [[ -z "${slap_serve_started-}" ]]; then
+        coproc {SLAP} serve
+        typeset -g slap_serve_started=1
+    fi
+    printf '%s\0' "$#" "$config" "$@" >&p
+    IFS= read -r -d '' -p ret || return 1
+    IFS= read -r -d '' -p slap_code || return 1
+    IFS= read -r -d '' -p err || return 1
+    [[ -n "$err" ]] && print -r -- "$err" >&2
+    return "$ret"
+}"#;
+
+#[cfg(unix)]
+fn os_string(bytes: Vec<u8>) -> OsString {
+    use std::os::unix::ffi::OsStringExt;
+    OsString::from_vec(bytes)
+}
+
+#[cfg(not(unix))]
+fn os_string(bytes: Vec<u8>) -> OsString {
+    String::from_utf8_lossy(&bytes).into_owned().into()
+}
+
+fn read_field(input: &mut impl BufRead) -> anyhow::Result<Option<Vec<u8>>> {
+    let mut field = Vec::new();
+    if input.read_until(0, &mut field)? == 0 {
+        return Ok(None);
+    }
+    if field.pop() != Some(0) {
+        anyhow::bail!("Truncated request, every field must be terminated by a NUL byte");
+    }
+    Ok(Some(field))
+}
+
+// How many configs are kept loaded, the server lives as long as the shell that started it.
+const CACHED_CONFIGS: usize = 16;
+
+self_cell::self_cell!(
+    // A config with its clap app, that borrows from it, so the app isn't built again.
+    struct LoadedConfig {
+        owner: Config,
+
+        #[covariant]
+        dependent: ExternalApp,
+    }
+);
+
+// The configs received most recently, keyed by `Config::hash`. The first one is the last used.
+#[derive(Default)]
+struct Cache {
+    configs: VecDeque<(u64, LoadedConfig)>,
+}
+
+impl Cache {
+    fn app(&mut self, source: String) -> Result<&ExternalApp<'_>, Error> {
+        let hash = Config::hash(&source);
+        match self.configs.iter().position(|(x, _)| *x == hash) {
+            Some(i) => {
+                let entry = self.configs.remove(i).unwrap();
+                self.configs.push_front(entry);
+            }
+            None => {
+                let config = LoadedConfig::try_new(load_config(&source)?, Config::app)?;
+                self.configs.truncate(CACHED_CONFIGS - 1);
+                self.configs.push_front((hash, config));
+            }
+        }
+        Ok(self.configs[0].1.borrow_dependent())
+    }
+
+    // Answers a request like `slap parse` would, returning its exit code, stdout and stderr.
+    fn answer(&mut self, config: Vec<u8>, args: Vec<OsString>) -> (i32, String, String) {
+        let config = match String::from_utf8(config) {
+            Ok(x) => x,
+            Err(_) => {
+                return (
+                    1,
+                    String::new(),
+                    "Error: The YAML config isn't valid UTF-8".into(),
+                )
+            }
+        };
+        let args = std::iter::once(OsString::from("parse")).chain(args);
+        let matches = match parse_cli()
+            .setting(AppSettings::ColorNever)
+            .get_matches_from_safe(args)
+        {
+            Ok(x) => x,
+            Err(e)
+                if e.kind == ErrorKind::HelpDisplayed || e.kind == ErrorKind::VersionDisplayed =>
+            {
+                return (0, String::new(), e.message)
+            }
+            Err(e) => return (1, String::new(), e.message),
+        };
+        let request = match ParseRequest::from_matches(&matches) {
+            Ok(x) => x,
+            Err(e) => return (1, String::new(), format!("Error: {:?}", e)),
+        };
+
+        let result = self.app(config).and_then(|app| {
+            app.parse_with(
+                &request.shell,
+                &request.external_args,
+                request.var_prefix,
+                &request.options,
+            )
+        });
+        match result {
+            Ok(code) => (0, code, String::new()),
+            Err(Error::Help(msg)) | Err(Error::Version(msg)) => (0, String::new(), msg),
+            Err(Error::Args(e)) => (1, String::new(), e.message),
+            Err(e) => (
+                1,
+                String::new(),
+                format!("Error: {:?}", anyhow::Error::from(e)),
+            ),
+        }
+    }
+}
+
+fn serve() -> anyhow::Result<()> {
+    let stdin = io::stdin();
+    let mut input = stdin.lock();
+    let stdout = io::stdout();
+    let mut output = stdout.lock();
+    let mut cache = Cache::default();
+
+    while let Some(count) = read_field(&mut input)? {
+        let count = String::from_utf8_lossy(&count)
+            .parse::<usize>()
+            .context("Invalid request, it must start with the number of arguments")?;
+        let config = read_field(&mut input)?.context("Truncated request, missing the config")?;
+        let mut args = Vec::with_capacity(count);
+        for _ in 0..count {
+            let arg = read_field(&mut input)?.context("Truncated request, missing arguments")?;
+            args.push(os_string(arg));
+        }
+
+        let (code, stdout, stderr) = cache.answer(config, args);
+        write!(output, "{}\0{}\0{}\0", code, stdout, stderr)?;
+        output.flush()?;
+    }
+
+    Ok(())
+}
+
+pub fn serve_subcmd(matches: &ArgMatches) -> anyhow::Result<()> {
+    let client = match matches.value_of("client") {
+        Some("bash") => BASH_CLIENT,
+        Some("zsh") => ZSH_CLIENT,
+        _ => return serve(),
+    };
+    // The client starts the same slap binary that printed it.
+    let slap = env::current_exe()?;
+    let slap = format!("'{}'", slap.display().to_string().replace('\'', r#"'\''"#));
+    println!("{}", client.replace("{SLAP}", &slap));
+    Ok(())
+}
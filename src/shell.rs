@@ -1,194 +1,718 @@
 use {
-    crate::ident_type::IdentType,
+    crate::{
+        extensions::{Extensions, ValueType},
+        ident_type::IdentType,
+        template::Template,
+        value_kind::ValueKind,
+        var_names::{Suffix, VarNames},
+    },
     anyhow::{bail, Context},
-    std::convert::TryFrom,
+    std::{borrow::Cow, convert::TryFrom, ffi::OsStr},
 };
 
+#[cfg(unix)]
+fn os_str_bytes(s: &OsStr) -> Cow<'_, [u8]> {
+    use std::os::unix::ffi::OsStrExt;
+    Cow::Borrowed(s.as_bytes())
+}
+
+// Outside of unix an `OsStr` isn't a sequence of bytes, the best we can do is a lossy conversion.
+#[cfg(not(unix))]
+fn os_str_bytes(s: &OsStr) -> Cow<'_, [u8]> {
+    match s.to_string_lossy() {
+        Cow::Borrowed(x) => Cow::Borrowed(x.as_bytes()),
+        Cow::Owned(x) => Cow::Owned(x.into_bytes()),
+    }
+}
+
+// The valid UTF-8 parts of `bytes`, each one followed by the invalid bytes after it, for the
+// shells that have no escapes for bytes in strings.
+pub(crate) fn utf8_parts(bytes: &[u8]) -> Vec<(Cow<'_, str>, &[u8])> {
+    let mut parts = Vec::new();
+    let mut rest = bytes;
+    while !rest.is_empty() {
+        let (valid, len) = match std::str::from_utf8(rest) {
+            Ok(x) => (x.len(), 0),
+            Err(e) => (
+                e.valid_up_to(),
+                e.error_len().unwrap_or(rest.len() - e.valid_up_to()),
+            ),
+        };
+        let (valid, invalid) = rest.split_at(valid);
+        let (invalid, next) = invalid.split_at(len);
+        // Lossless, `valid` is valid UTF-8.
+        parts.push((String::from_utf8_lossy(valid), invalid));
+        rest = next;
+    }
+    parts
+}
+
+// The octal escapes of `printf` for `bytes`.
+fn printf_escapes(bytes: &[u8]) -> String {
+    bytes.iter().map(|x| format!("\\{:03o}", x)).collect()
+}
+
+// Variables assigned with `set` are local to the function, the ones assigned with `global` are
+// visible everywhere.
+fn murex_scope(local: bool) -> &'static str {
+    if local {
+        "set"
+    } else {
+        "global"
+    }
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const CHARS: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut s = String::new();
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (idx, x)| n | u32::from(*x) << (16 - idx * 8));
+        for idx in 0..4 {
+            if idx <= chunk.len() {
+                s.push(CHARS[(n >> (18 - idx * 6) & 0x3f) as usize] as char);
+            } else {
+                s.push('=');
+            }
+        }
+    }
+    s
+}
+
+/// Options that change the code generated by `Shell::parse`.
+#[derive(Clone, Default)]
+pub struct ParseOptions {
+    /// Emit the values of single-valued arguments as scalars and flags as booleans, instead of
+    /// always emitting arrays. Only arguments that can take multiple values are emitted as
+    /// arrays.
+    pub scalars: bool,
+    /// How the names of the variables are built.
+    pub var_names: VarNames,
+    /// Declare the variables local to the function that evaluates the code.
+    pub local: bool,
+    /// Unset every variable that the config can generate before assigning the new ones, so
+    /// values of previous parses don't linger. With `local` they are declared local instead.
+    pub reset: bool,
+}
+
 #[derive(Clone)]
 pub enum Shell {
     Bash,
     Elvish,
     Fish,
+    // ksh93 and mksh.
+    Ksh,
+    Murex,
     PowerShell,
+    // The rc of Plan 9, as shipped by plan9port and 9base.
+    Rc,
+    Tcsh,
+    // A language described by a template file.
+    Template(Box<Template>),
+    Xonsh,
+    // The YSH language of Oils, OSH is compatible with bash.
+    Ysh,
     Zsh,
 }
 
+// Reserved by Python, they can't be the names of variables. The soft keywords, like `match`,
+// can.
+const PYTHON_KEYWORDS: [&str; 35] = [
+    "False", "None", "True", "and", "as", "assert", "async", "await", "break", "class", "continue",
+    "def", "del", "elif", "else", "except", "finally", "for", "from", "global", "if", "import",
+    "in", "is", "lambda", "nonlocal", "not", "or", "pass", "raise", "return", "try", "while",
+    "with", "yield",
+];
+
 impl Shell {
-    pub const SHELLS: [&'static str; 5] = ["bash", "elvish", "fish", "pwsh", "zsh"];
+    pub const SHELLS: [&'static str; 11] = [
+        "bash", "elvish", "fish", "ksh", "murex", "pwsh", "rc", "tcsh", "xonsh", "ysh", "zsh",
+    ];
 
-    fn ident_check<'a>(&self, s: &'a str, ident_type: &IdentType) -> anyhow::Result<&'a str> {
+    pub(crate) fn ident_check<'a>(
+        &self,
+        s: &'a str,
+        ident_type: &IdentType,
+    ) -> anyhow::Result<&'a str> {
         let re = ident_type.re(self);
+        if let (Self::Xonsh, IdentType::Head) = (self, ident_type) {
+            if PYTHON_KEYWORDS.contains(&s) {
+                bail!("`{}` is not a valid identifier, it's a Python keyword", s);
+            }
+        }
         if re.is_match(s) {
             Ok(s)
         } else {
             bail!(
                 "`{}` is not a valid identifier, it must conform to this regex: `{}`",
                 s,
-                re.to_string(),
+                re,
             )
         }
     }
 
-    fn str_escape(&self, s: &str) -> String {
-        let mut s = s.replace(
-            '\'',
-            match self {
-                Self::Fish => "\\'",
-                Self::Bash | Self::Elvish | Self::Zsh => r#"'"'"'"#,
-                Self::PowerShell => "''",
-            },
-        );
-        s.insert(0, '\'');
-        s.push('\'');
-        s
+    pub(crate) fn str_escape(&self, s: &str) -> String {
+        if let Self::Template(template) = self {
+            return template.str_escape(s);
+        }
+        let mut escaped = String::with_capacity(s.len() + 3);
+        // J8 string, unlike double quoted strings it never interpolates.
+        if let Self::Ysh = self {
+            escaped.push('u');
+        }
+        let quote = if let Self::Murex = self { '"' } else { '\'' };
+        escaped.push(quote);
+        self.push_escaped(s, &mut escaped);
+        escaped.push(quote);
+        escaped
     }
 
-    fn array_escape(&self, xs: &[&str]) -> String {
-        let mut s = match self {
-            Self::Fish => String::new(),
-            Self::Bash | Self::Zsh => "(".into(),
-            Self::Elvish => "[".into(),
-            Self::PowerShell => "@(".into(),
-        };
-        let len = xs.len();
-        for (idx, x) in xs.iter().enumerate() {
-            s.push_str(&self.str_escape(x));
-            if idx < len - 1 {
-                if let Self::PowerShell = self {
-                    s.push(',');
+    // Pushes the characters of `s` escaped for the quotes opened by `str_escape`.
+    fn push_escaped(&self, s: &str, escaped: &mut String) {
+        for x in s.chars() {
+            match (self, x) {
+                // Inside single quotes fish treats backslashes as escapes too.
+                (Self::Fish, '\\') => escaped.push_str("\\\\"),
+                (Self::Fish, '\'') => escaped.push_str("\\'"),
+                (Self::Bash, '\'')
+                | (Self::Elvish, '\'')
+                | (Self::Ksh, '\'')
+                | (Self::Zsh, '\'') => escaped.push_str(r#"'"'"'"#),
+                // Nothing can be escaped inside single quotes in csh but `!` and newlines, with
+                // a backslash. Outside of quotes `\!` is a `!` even without history substitution.
+                (Self::Tcsh, '\'') => escaped.push_str(r"'\''"),
+                (Self::Tcsh, '!') => escaped.push_str(r"'\!'"),
+                (Self::Tcsh, '\n') => escaped.push_str("\\\n"),
+                // Python string literal.
+                (Self::Xonsh, '\\') => escaped.push_str("\\\\"),
+                (Self::Xonsh, '\'') => escaped.push_str("\\'"),
+                (Self::Xonsh, x) if x.is_control() => {
+                    escaped.push_str(&format!("\\x{:02x}", u32::from(x)))
                 }
-                s.push(' ');
+                // Double quotes, murex expands variables and the home directory in them.
+                (Self::Murex, '\\')
+                | (Self::Murex, '"')
+                | (Self::Murex, '$')
+                | (Self::Murex, '@')
+                | (Self::Murex, '~') => {
+                    escaped.push('\\');
+                    escaped.push(x);
+                }
+                (Self::Murex, '\n') => escaped.push_str("\\n"),
+                (Self::Murex, '\r') => escaped.push_str("\\r"),
+                (Self::Murex, '\t') => escaped.push_str("\\t"),
+                (Self::Rc, '\'') => escaped.push_str("''"),
+                (Self::Ysh, '\\') => escaped.push_str("\\\\"),
+                (Self::Ysh, '\'') => escaped.push_str("\\'"),
+                (Self::Ysh, x) if x.is_control() => {
+                    escaped.push_str(&format!("\\u{{{:x}}}", u32::from(x)))
+                }
+                // PowerShell treats typographic single quotes as single quotes.
+                (Self::PowerShell, '\'')
+                | (Self::PowerShell, '\u{2018}')
+                | (Self::PowerShell, '\u{2019}')
+                | (Self::PowerShell, '\u{201a}')
+                | (Self::PowerShell, '\u{201b}') => {
+                    escaped.push(x);
+                    escaped.push(x);
+                }
+                _ => escaped.push(x),
             }
         }
+    }
+
+    // Escapes a string that can contain invalid UTF-8 data, preserving every byte.
+    fn os_str_escape(&self, s: &OsStr) -> String {
+        if let Some(s) = s.to_str() {
+            return self.str_escape(s);
+        }
+
+        let bytes = os_str_bytes(s);
+        let mut s = String::new();
         match self {
-            Self::Bash | Self::PowerShell | Self::Zsh => s.push(')'),
-            Self::Elvish => s.push(']'),
-            _ => {}
+            // ANSI-C quoting.
+            Self::Bash | Self::Zsh => {
+                s.push_str("$'");
+                for x in bytes.iter() {
+                    match x {
+                        b' '..=b'~' if *x != b'\'' && *x != b'\\' => s.push(*x as char),
+                        _ => s.push_str(&format!("\\x{:02x}", x)),
+                    }
+                }
+                s.push('\'');
+            }
+            // Every byte is escaped, so an escape is never followed by a hex digit that some
+            // versions of ksh would read as part of it.
+            Self::Ksh => {
+                s.push_str("$'");
+                for x in bytes.iter() {
+                    s.push_str(&format!("\\x{:02x}", x));
+                }
+                s.push('\'');
+            }
+            // Strings decoded like the arguments of the xonsh process, undecodable bytes become
+            // surrogates that `os.fsencode` turns back into the same bytes.
+            Self::Xonsh => {
+                s.push_str("__import__('os').fsdecode(b'");
+                for x in bytes.iter() {
+                    match x {
+                        b' '..=b'~' if *x != b'\'' && *x != b'\\' => s.push(*x as char),
+                        _ => s.push_str(&format!("\\x{:02x}", x)),
+                    }
+                }
+                s.push_str("')");
+            }
+            // The shells without escapes for bytes get them from `printf`, in command
+            // substitutions concatenated to the quoted valid parts of the string.
+            Self::Tcsh => {
+                for (valid, invalid) in utf8_parts(&bytes) {
+                    s.push_str(&self.str_escape(&valid));
+                    if !invalid.is_empty() {
+                        s.push_str(&format!("\"`printf '{}'`\"", printf_escapes(invalid)));
+                    }
+                }
+            }
+            Self::Rc => {
+                let mut parts = Vec::new();
+                for (valid, invalid) in utf8_parts(&bytes) {
+                    parts.push(self.str_escape(&valid));
+                    if !invalid.is_empty() {
+                        parts.push(format!("`{{printf '{}'}}", printf_escapes(invalid)));
+                    }
+                }
+                s.push_str(&parts.join("^"));
+            }
+            Self::Murex => {
+                s.push('"');
+                for (valid, invalid) in utf8_parts(&bytes) {
+                    self.push_escaped(&valid, &mut s);
+                    if !invalid.is_empty() {
+                        s.push_str(&format!("${{printf '{}'}}", printf_escapes(invalid)));
+                    }
+                }
+                s.push('"');
+            }
+            // J8 byte string.
+            Self::Ysh => {
+                s.push_str("b'");
+                for x in bytes.iter() {
+                    match x {
+                        b' '..=b'~' if *x != b'\'' && *x != b'\\' => s.push(*x as char),
+                        _ => s.push_str(&format!("\\y{:02x}", x)),
+                    }
+                }
+                s.push('\'');
+            }
+            // Unquoted, `\X` produces a raw byte while `\x` an ASCII character.
+            Self::Fish => {
+                for x in bytes.iter() {
+                    match x {
+                        b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'_' | b'-' | b'.' | b'/' => {
+                            s.push(*x as char)
+                        }
+                        0x80..=0xff => s.push_str(&format!("\\X{:02x}", x)),
+                        _ => s.push_str(&format!("\\x{:02x}", x)),
+                    }
+                }
+            }
+            Self::Elvish => {
+                s.push('"');
+                for x in bytes.iter() {
+                    match x {
+                        b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'_' | b'-' | b'.' | b'/' => {
+                            s.push(*x as char)
+                        }
+                        _ => s.push_str(&format!("\\x{:02x}", x)),
+                    }
+                }
+                s.push('"');
+            }
+            // PowerShell strings are UTF-16, so they can't contain arbitrary bytes, the value
+            // becomes a byte array decoded from base64.
+            Self::PowerShell => {
+                s.push_str(&format!(
+                    "([System.Convert]::FromBase64String('{}'))",
+                    base64(&bytes)
+                ));
+            }
+            Self::Template(template) => s.push_str(&template.bytes_escape(&bytes)),
         }
         s
     }
 
-    fn assignment(&self, var_ident: &str, val: &str) -> String {
+    // Builds an array out of already escaped values, `xs` can be empty.
+    fn array(&self, xs: &[String]) -> String {
         match self {
-            Self::Fish => format!("set {} {}", var_ident, val),
-            Self::Bash | Self::Zsh => format!("{}={}", var_ident, val),
-            Self::Elvish => format!("{} = {}", var_ident, val),
+            // Fish variables are lists, an empty value makes an empty list, see `assignment`.
+            Self::Fish => xs.join(" "),
+            Self::Bash | Self::Zsh => format!("({})", xs.join(" ")),
+            // Arrays can't be values in ksh, see `array_assignment`.
+            Self::Ksh => xs.join(" "),
+            Self::Elvish => format!("[{}]", xs.join(" ")),
+            Self::Rc => format!("({})", xs.join(" ")),
+            Self::Murex => format!("%[{}]", xs.join(" ")),
+            Self::Tcsh => format!("( {} )", xs.join(" ")),
+            Self::Template(template) => template.list(xs),
+            Self::Xonsh | Self::Ysh => format!("[{}]", xs.join(", ")),
+            // The unary comma avoids unrolling a single value that is an array itself, like the
+            // byte arrays produced by `os_str_escape`.
+            Self::PowerShell if xs.len() == 1 => format!("@(,{})", xs[0]),
+            Self::PowerShell => format!("@({})", xs.join(", ")),
+        }
+    }
+
+    fn boolean(&self, b: bool) -> String {
+        match (self, b) {
+            (Self::Bash, _)
+            | (Self::Fish, _)
+            | (Self::Ksh, _)
+            | (Self::Murex, _)
+            | (Self::Rc, _)
+            | (Self::Tcsh, _)
+            | (Self::Ysh, _)
+            | (Self::Zsh, _) => b.to_string(),
+            (Self::Elvish, true) | (Self::PowerShell, true) => "$true".into(),
+            (Self::Elvish, false) | (Self::PowerShell, false) => "$false".into(),
+            (Self::Template(template), _) => template.boolean(b),
+            (Self::Xonsh, true) => "True".into(),
+            (Self::Xonsh, false) => "False".into(),
+        }
+    }
+
+    // The literal of a typed value in the native type of the shell, `None` for strings.
+    fn typed_value(&self, val: &str, value_type: ValueType) -> Option<String> {
+        match value_type {
+            ValueType::Bool => ValueType::parse_bool(val).map(|x| self.boolean(x)),
+            ValueType::Int => ValueType::parse_int(val).map(|x| match self {
+                Self::Bash
+                | Self::Fish
+                | Self::Ksh
+                | Self::Murex
+                | Self::Rc
+                | Self::Tcsh
+                | Self::Template(_)
+                | Self::Xonsh
+                | Self::Ysh
+                | Self::Zsh => x.to_string(),
+                Self::Elvish => format!("(num {})", x),
+                Self::PowerShell if i32::try_from(x).is_ok() => format!("([int]{})", x),
+                Self::PowerShell => format!("([long]{})", x),
+            }),
+            ValueType::String => None,
+        }
+    }
+
+    // Assignment of integers, that are typed in murex. bash, ksh and zsh get the checked digits
+    // without the integer attribute, that would stay on the variable and make later assignments
+    // of untyped values evaluate them as arithmetic expressions, running their command
+    // substitutions.
+    fn int_assignment(&self, var_ident: &str, val: &str, local: bool) -> String {
+        match self {
+            Self::Murex => format!("{} int {} = {}", murex_scope(local), var_ident, val),
+            _ => self.assignment(var_ident, val, local),
+        }
+    }
+
+    // Assignment of an array of already escaped values, `xs` can be empty.
+    fn array_assignment(&self, var_ident: &str, xs: &[String], local: bool) -> String {
+        match self {
+            // `set -A` works in both ksh93 and mksh, unlike `name=(...)`. `--` keeps values
+            // starting with a dash from being read as options.
+            Self::Ksh => {
+                let mut s = String::new();
+                if local {
+                    s.push_str(&format!("typeset {}\n", var_ident));
+                }
+                if xs.is_empty() {
+                    if !local {
+                        s.push_str(&format!("unset {}", var_ident));
+                    }
+                } else {
+                    s.push_str(&format!("set -A {} -- {}", var_ident, self.array(xs)));
+                }
+                s.trim_end().into()
+            }
+            _ => self.assignment(var_ident, &self.array(xs), local),
+        }
+    }
+
+    fn assignment(&self, var_ident: &str, val: &str, local: bool) -> String {
+        match self {
+            Self::Fish => {
+                let mut s = String::from(if local { "set -l " } else { "set " });
+                s.push_str(var_ident);
+                // An empty value makes an empty list.
+                if !val.is_empty() {
+                    s.push(' ');
+                    s.push_str(val);
+                }
+                s
+            }
+            Self::Bash | Self::Zsh if local => format!("local {}={}", var_ident, val),
+            Self::Ksh if local => format!("typeset {}={}", var_ident, val),
+            Self::Bash | Self::Ksh | Self::Zsh => format!("{}={}", var_ident, val),
+            Self::Murex => format!("{} {} = {}", murex_scope(local), var_ident, val),
+            // rc has no local variables, other than the ones of a single command.
+            Self::Rc => format!("{}={}", var_ident, val),
+            // csh has no functions, the variables are always the ones of the shell.
+            Self::Tcsh => format!("set {} = {}", var_ident, val),
+            Self::Template(template) => template.assignment(var_ident, val, local),
+            Self::Elvish if local => format!("var {} = {}", var_ident, val),
+            // `--local` is rejected by `parse` for xonsh.
+            Self::Elvish | Self::Xonsh => format!("{} = {}", var_ident, val),
+            // Local variables are declared beforehand by `parse`, `var` can't declare a variable
+            // twice.
+            Self::Ysh if local => format!("setvar {} = {}", var_ident, val),
+            Self::Ysh => format!("setglobal {} = {}", var_ident, val),
             Self::PowerShell => format!(
-                "Set-Variable -Name {} -Value {}",
+                "Set-Variable {}-Name {} -Value {}",
+                if local { "-Scope Local " } else { "" },
                 self.str_escape(var_ident),
                 val
             ),
         }
     }
 
-    // NOTE: In the future we could add an option to use associative arrays instead of arrays for
-    // elvish and powershell.
-    fn parse_(
+    // Unsets the variables, that may not be set. Local variables are declared without a value
+    // instead, that hides the variables of the calling scopes without unsetting them.
+    fn unset(&self, var_idents: &[String], local: bool) -> String {
+        match self {
+            Self::Bash | Self::Zsh if local => format!("local {}", var_idents.join(" ")),
+            Self::Ksh if local => format!("typeset {}", var_idents.join(" ")),
+            Self::Bash | Self::Ksh | Self::Tcsh | Self::Zsh => {
+                format!("unset {}", var_idents.join(" "))
+            }
+            Self::Fish => var_idents
+                .iter()
+                .map(|x| format!("set {} {}", if local { "-l" } else { "-e" }, x))
+                .collect::<Vec<_>>()
+                .join("\n"),
+            // Variables that aren't defined can't be deleted, they are declared again with the
+            // `$nil` value instead.
+            Self::Elvish => format!("var {}", var_idents.join(" ")),
+            Self::Xonsh => format!("{} = None", var_idents.join(" = ")),
+            Self::Template(template) => template.unset(var_idents, local),
+            Self::Murex => var_idents
+                .iter()
+                .map(|x| format!("{} json {} = null", murex_scope(local), x))
+                .collect::<Vec<_>>()
+                .join("\n"),
+            Self::Rc => var_idents
+                .iter()
+                .map(|x| format!("{}=()", x))
+                .collect::<Vec<_>>()
+                .join("\n"),
+            Self::Ysh => var_idents
+                .iter()
+                .map(|x| format!("{} {} = null", if local { "var" } else { "setglobal" }, x))
+                .collect::<Vec<_>>()
+                .join("\n"),
+            Self::PowerShell => {
+                let names = var_idents
+                    .iter()
+                    .map(|x| self.str_escape(x))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                if local {
+                    format!("Set-Variable -Scope Local -Name {} -Value $null", names)
+                } else {
+                    format!(
+                        "Remove-Variable -Name {} -ErrorAction SilentlyContinue",
+                        names
+                    )
+                }
+            }
+        }
+    }
+
+    pub(crate) fn parse(
         &self,
-        matches: &clap::ArgMatches,
+        app: &clap::App,
+        extensions: &Extensions,
+        matches: clap::ArgMatches,
         var_prefix: Option<&str>,
-        // Subcommands are recursive, used to mantain the subcommand prefix for variables.
-        subcommands_prefixes: Option<Vec<&str>>,
+        options: &ParseOptions,
     ) -> anyhow::Result<String> {
-        let vprefix = {
-            let mut s = String::new();
-            if let Some(vprefix) = var_prefix {
-                s = self.ident_check(vprefix, &IdentType::Head)?.into();
-            }
-            s
-        };
-
-        let subcommands_ident = if let Some(ref xs) = subcommands_prefixes {
-            format!("{}_", xs.join("_"))
-        } else {
-            String::new()
+        // The code evaluated by `execx` assigns the variables of the scope that evaluates it,
+        // global at the top level, and Python can't declare them local.
+        if options.local && matches!(self, Self::Xonsh) {
+            bail!("xonsh has no local variables, `--local` can't be used with it");
+        }
+        let prefix = match var_prefix {
+            Some(x) => self.ident_check(x, &IdentType::Head)?,
+            None => "",
         };
-        let subcommands_ident = if subcommands_ident.is_empty() {
-            subcommands_ident
-        } else {
-            self.ident_check(&subcommands_ident, &IdentType::Tail)?
-                .into()
+        let mut codegen = Codegen {
+            shell: self,
+            extensions,
+            options,
+            prefix,
+            buffer: String::new(),
         };
 
-        let mut buffer = String::new();
+        // Local variables of ysh must be declared with `var` before they are assigned.
+        if options.reset || (options.local && matches!(self, Self::Ysh)) {
+            let vars = options.var_names.check_collisions(app, prefix)?;
+            for x in vars.iter() {
+                self.ident_check(x, &IdentType::Head)?;
+            }
+            codegen.push(self.unset(&vars, options.local));
+        }
+        codegen.assign(&[], "success", None, self.str_escape("true"))?;
+        codegen.app(app, &matches, &mut Vec::new())?;
+        Ok(codegen.buffer.trim_end().into())
+    }
+}
 
-        if subcommands_prefixes.is_none() {
-            buffer.push_str(
-                &self.assignment(&format!("{}success", vprefix), &self.str_escape("true")),
-            );
-            buffer.push('\n');
+// Generates the code of `Shell::parse`.
+struct Codegen<'a> {
+    shell: &'a Shell,
+    extensions: &'a Extensions,
+    options: &'a ParseOptions,
+    prefix: &'a str,
+    buffer: String,
+}
+
+impl<'a> Codegen<'a> {
+    // The name of a variable, checked for validity. Collisions are checked beforehand by
+    // `VarNames::check_collisions`.
+    fn var(&self, path: &[&str], arg: &str, suffix: Option<Suffix>) -> anyhow::Result<String> {
+        let name = self
+            .options
+            .var_names
+            .name(self.prefix, path, arg, suffix)?;
+        self.shell.ident_check(&name, &IdentType::Head)?;
+        Ok(name)
+    }
+
+    fn assign(
+        &mut self,
+        path: &[&str],
+        arg: &str,
+        suffix: Option<Suffix>,
+        val: String,
+    ) -> anyhow::Result<()> {
+        let var = self.var(path, arg, suffix)?;
+        self.push(self.shell.assignment(&var, &val, self.options.local));
+        Ok(())
+    }
+
+    fn assign_array(
+        &mut self,
+        path: &[&str],
+        arg: &str,
+        suffix: Option<Suffix>,
+        xs: &[String],
+    ) -> anyhow::Result<()> {
+        let var = self.var(path, arg, suffix)?;
+        self.push(self.shell.array_assignment(&var, xs, self.options.local));
+        Ok(())
+    }
+
+    fn push(&mut self, code: String) {
+        self.buffer.push_str(&code);
+        self.buffer.push('\n');
+    }
+
+    // NOTE: In the future we could add an option to use associative arrays instead of arrays for
+    // elvish and powershell.
+    fn app<'m>(
+        &mut self,
+        app: &clap::App,
+        matches: &'m clap::ArgMatches,
+        // Subcommands are recursive, used to mantain the subcommand prefix for variables.
+        path: &mut Vec<&'m str>,
+    ) -> anyhow::Result<()> {
+        let shell = self.shell;
+        for x in path.iter() {
+            shell.ident_check(x, &IdentType::Tail)?;
         }
 
         if let Some(ref usage) = matches.usage {
-            let clap_usage = self.str_escape(usage);
-            buffer.push_str(&self.assignment(
-                &format!("{}{}usage", vprefix, subcommands_ident),
-                &clap_usage,
-            ));
-            buffer.push('\n');
+            let clap_usage = shell.str_escape(usage);
+            self.assign(path, "usage", None, clap_usage)?;
         }
 
         if let Some(ref subcommand) = matches.subcommand {
-            let clap_subcommand = self.str_escape(&subcommand.name);
-            buffer.push_str(&self.assignment(
-                &format!("{}{}subcommand", vprefix, subcommands_ident),
-                &clap_subcommand,
-            ));
-            buffer.push('\n');
-
-            let mut subcommands_prefixes = subcommands_prefixes.unwrap_or_default();
-            subcommands_prefixes.push(&subcommand.name);
-            buffer.push_str(&self.parse_(
-                &subcommand.matches,
-                var_prefix,
-                Some(subcommands_prefixes),
-            )?)
+            let clap_subcommand = shell.str_escape(&subcommand.name);
+            self.assign(path, "subcommand", None, clap_subcommand)?;
+
+            let subcommand_app = app
+                .p
+                .subcommands
+                .iter()
+                .find(|x| x.get_name() == subcommand.name)
+                .context("Matched a subcommand that isn't defined")?;
+            path.push(&subcommand.name);
+            self.app(subcommand_app, &subcommand.matches, path)?;
+            path.pop();
         }
 
         for (name, arg) in &matches.args {
-            let arg_name = self.ident_check(name, &IdentType::Tail)?;
+            let arg_name = shell.ident_check(name, &IdentType::Tail)?;
 
-            let clap_occurs = self.str_escape(&arg.occurs.to_string());
-            buffer.push_str(&self.assignment(
-                &format!("{}{}{}_occurs", vprefix, subcommands_ident, arg_name),
-                &clap_occurs,
-            ));
-            buffer.push('\n');
+            let clap_occurs = shell.str_escape(&arg.occurs.to_string());
+            self.assign(path, arg_name, Some(Suffix::Occurs), clap_occurs)?;
 
             let clap_indices = arg
                 .indices
                 .iter()
-                .map(|x| x.to_string())
+                .map(|x| shell.str_escape(&x.to_string()))
                 .collect::<Vec<_>>();
-            let clap_indices = clap_indices.iter().map(|x| x.as_str()).collect::<Vec<_>>();
-            let clap_indices = self.array_escape(&clap_indices);
-            buffer.push_str(&self.assignment(
-                &format!("{}{}{}_indices", vprefix, subcommands_ident, arg_name),
-                &clap_indices,
-            ));
-            buffer.push('\n');
+            self.assign_array(path, arg_name, Some(Suffix::Indices), &clap_indices)?;
 
-            let mut clap_vals = Vec::new();
-            for val in &arg.vals {
-                clap_vals.push(val.to_str().context("String contains invalid UTF-8 data")?);
-            }
-            let clap_vals = self.array_escape(&clap_vals);
-            buffer.push_str(&self.assignment(
-                &format!("{}{}{}_vals", vprefix, subcommands_ident, arg_name),
-                &clap_vals,
-            ));
-            buffer.push('\n');
+            let value_type = self
+                .extensions
+                .arg(path, name)
+                .and_then(|x| x.value_type)
+                .unwrap_or(ValueType::String);
+            let clap_vals = arg
+                .vals
+                .iter()
+                .map(|x| {
+                    x.to_str()
+                        .and_then(|x| shell.typed_value(x, value_type))
+                        .unwrap_or_else(|| shell.os_str_escape(x))
+                })
+                .collect::<Vec<_>>();
+            let clap_vals_count = shell.str_escape(&clap_vals.len().to_string());
+            let kind = if self.options.scalars {
+                ValueKind::of(app, name).unwrap_or(ValueKind::Multiple)
+            } else {
+                ValueKind::Multiple
+            };
+            let var = self.var(path, arg_name, Some(Suffix::Vals))?;
+            let int = matches!(value_type, ValueType::Int);
+            let local = self.options.local;
+            self.push(match kind {
+                ValueKind::Flag => shell.assignment(&var, &shell.boolean(true), local),
+                ValueKind::Single if clap_vals.len() == 1 && int => {
+                    shell.int_assignment(&var, &clap_vals[0], local)
+                }
+                ValueKind::Single if clap_vals.len() == 1 => {
+                    shell.assignment(&var, &clap_vals[0], local)
+                }
+                _ => shell.array_assignment(&var, &clap_vals, local),
+            });
+
+            self.assign(path, arg_name, Some(Suffix::ValsCount), clap_vals_count)?;
         }
 
-        Ok(buffer)
-    }
+        // Flags that weren't passed are booleans too.
+        if self.options.scalars {
+            for name in ValueKind::flags(app) {
+                // Always added by slap, they are never present when code is generated.
+                if name == "help" || name == "version" || matches.args.contains_key(name) {
+                    continue;
+                }
+                let arg_name = shell.ident_check(name, &IdentType::Tail)?;
+                self.assign(path, arg_name, Some(Suffix::Vals), shell.boolean(false))?;
+            }
+        }
 
-    pub fn parse(
-        &self,
-        matches: clap::ArgMatches,
-        var_prefix: Option<&str>,
-    ) -> anyhow::Result<String> {
-        Ok(self.parse_(&matches, var_prefix, None)?.trim_end().into())
+        Ok(())
     }
 }
 
@@ -200,21 +724,38 @@ impl TryFrom<&str> for Shell {
             "bash" => Ok(Shell::Bash),
             "elvish" => Ok(Shell::Elvish),
             "fish" => Ok(Shell::Fish),
+            "ksh" => Ok(Shell::Ksh),
+            "murex" => Ok(Shell::Murex),
             "pwsh" => Ok(Shell::PowerShell),
+            "rc" => Ok(Shell::Rc),
+            "tcsh" => Ok(Shell::Tcsh),
+            "template" => bail!("The template shell is built from a file by `Template::load`"),
+            "xonsh" => Ok(Shell::Xonsh),
+            "ysh" => Ok(Shell::Ysh),
             "zsh" => Ok(Shell::Zsh),
             _ => bail!("Shell must be one of {:?}", Shell::SHELLS),
         }
     }
 }
 
-impl<'a> Into<clap::Shell> for &'a Shell {
-    fn into(self) -> clap::Shell {
-        match *self {
-            Shell::Bash => clap::Shell::Bash,
-            Shell::Elvish => clap::Shell::Elvish,
-            Shell::Fish => clap::Shell::Fish,
-            Shell::PowerShell => clap::Shell::PowerShell,
-            Shell::Zsh => clap::Shell::Zsh,
+// Completions are generated by clap, that supports only some of the shells.
+impl TryFrom<&Shell> for clap::Shell {
+    type Error = anyhow::Error;
+
+    fn try_from(shell: &Shell) -> anyhow::Result<Self> {
+        match *shell {
+            Shell::Bash => Ok(clap::Shell::Bash),
+            Shell::Elvish => Ok(clap::Shell::Elvish),
+            Shell::Fish => Ok(clap::Shell::Fish),
+            Shell::PowerShell => Ok(clap::Shell::PowerShell),
+            Shell::Zsh => Ok(clap::Shell::Zsh),
+            Shell::Ksh => bail!("Completions can't be generated for ksh"),
+            Shell::Murex => bail!("Completions can't be generated for murex"),
+            Shell::Rc => bail!("Completions can't be generated for rc"),
+            Shell::Tcsh => bail!("Completions can't be generated for tcsh"),
+            Shell::Template(_) => bail!("Completions can't be generated for templates"),
+            Shell::Xonsh => bail!("Completions can't be generated for xonsh"),
+            Shell::Ysh => bail!("Completions can't be generated for ysh"),
         }
     }
 }
@@ -0,0 +1,252 @@
+use {
+    crate::shell::utf8_parts,
+    anyhow::{bail, Context},
+    lazy_static::lazy_static,
+    regex::Regex,
+    std::collections::HashMap,
+};
+
+lazy_static! {
+    static ref PLACEHOLDER_RE: Regex = Regex::new(r"\{[a-z]+\}").unwrap();
+}
+
+/// The code generation of a language that slap doesn't support natively, loaded from a template
+/// file by `slap parse --template FILE`.
+///
+/// Every line of the file is a directive followed by a space and its value, the rest of the
+/// line taken verbatim. Empty lines and lines starting with `#` are ignored.
+#[derive(Clone)]
+pub struct Template {
+    // `{str}` is the escaped string.
+    string: String,
+    escapes: HashMap<char, String>,
+    // Control characters without an escape, `{hex}`, `{dec}` and `{oct}` are their code point.
+    control: Option<String>,
+    // Bytes that aren't valid UTF-8, `{hex}`, `{dec}` and `{oct}` are their value. Without it
+    // they are replaced with U+FFFD.
+    byte: Option<String>,
+    // `{items}` are the items joined by `separator`.
+    list: String,
+    separator: String,
+    // `{name}` is the name of the variable and `{value}` its escaped value.
+    assign: String,
+    local_assign: Option<String>,
+    // `{name}` is the name of the variable, by default the variable is assigned an empty list.
+    unset: Option<String>,
+    true_value: Option<String>,
+    false_value: Option<String>,
+    ident: Regex,
+    // The VAR_PREFIX, that can't follow `--template` on the command line.
+    var_prefix: Option<String>,
+}
+
+// Replaces the placeholders of a template in a single pass, so placeholders contained in the
+// replacements aren't replaced. The placeholders are checked by `Template::load`.
+fn render(template: &str, values: &[(&str, &str)]) -> String {
+    let mut rendered = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        rendered.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let value = values.iter().find(|(name, _)| {
+            rest[1..]
+                .strip_prefix(name)
+                .is_some_and(|x| x.starts_with('}'))
+        });
+        match value {
+            Some((name, value)) => {
+                rendered.push_str(value);
+                rest = &rest[name.len() + 2..];
+            }
+            None => {
+                rendered.push('{');
+                rest = &rest[1..];
+            }
+        }
+    }
+    rendered.push_str(rest);
+    rendered
+}
+
+fn code_point(template: &str, x: u32) -> String {
+    render(
+        template,
+        &[
+            ("hex", &format!("{:02x}", x)),
+            ("dec", &x.to_string()),
+            ("oct", &format!("{:03o}", x)),
+        ],
+    )
+}
+
+// The character of an `escape` directive, written as is or as `\n`, `\r`, `\t`, `\s` (space)
+// and `\\`.
+fn escaped_char(s: &str) -> anyhow::Result<char> {
+    let mut chars = s.chars();
+    match (chars.next(), chars.next(), chars.next()) {
+        (Some(x), None, _) => Ok(x),
+        (Some('\\'), Some(x), None) => match x {
+            'n' => Ok('\n'),
+            'r' => Ok('\r'),
+            't' => Ok('\t'),
+            's' => Ok(' '),
+            '\\' => Ok('\\'),
+            _ => bail!("Unknown escape `{}`", s),
+        },
+        _ => bail!("`{}` isn't a single character", s),
+    }
+}
+
+impl Template {
+    pub fn load(s: &str) -> anyhow::Result<Self> {
+        let mut directives = HashMap::new();
+        let mut escapes = HashMap::new();
+        for (idx, line) in s.lines().enumerate() {
+            let line = line.strip_suffix('\r').unwrap_or(line);
+            if line.trim().is_empty() || line.trim_start().starts_with('#') {
+                continue;
+            }
+            let (directive, value) = match line.find(' ') {
+                Some(x) => (&line[..x], &line[x + 1..]),
+                None => (line, ""),
+            };
+            let placeholders: &[&str] = match directive {
+                "escape" => {
+                    let (x, replacement) = match value.find(' ') {
+                        Some(x) => (&value[..x], &value[x + 1..]),
+                        None => (value, ""),
+                    };
+                    let x = escaped_char(x).with_context(|| format!("Line {}", idx + 1))?;
+                    escapes.insert(x, replacement.to_string());
+                    continue;
+                }
+                "string" => &["str"],
+                "control" | "byte" => &["hex", "dec", "oct"],
+                "list" => &["items"],
+                "assign" | "local-assign" => &["name", "value"],
+                "unset" => &["name"],
+                "separator" | "true" | "false" | "ident" | "var-prefix" => &[],
+                x => bail!("Line {}: unknown directive `{}`", idx + 1, x),
+            };
+            // Placeholders are the only thing that can't be written verbatim, so an unknown
+            // one is most likely a typo.
+            for x in PLACEHOLDER_RE.find_iter(value) {
+                let name = &x.as_str()[1..x.as_str().len() - 1];
+                if !placeholders.contains(&name) {
+                    bail!(
+                        "Line {}: unknown placeholder `{}` in `{}`",
+                        idx + 1,
+                        x.as_str(),
+                        directive
+                    );
+                }
+            }
+            if directives.insert(directive, value.to_string()).is_some() {
+                bail!("Line {}: `{}` is repeated", idx + 1, directive);
+            }
+        }
+
+        let mut required = |x: &str| {
+            directives
+                .remove(x)
+                .with_context(|| format!("The template must contain the `{}` directive", x))
+        };
+        let string = required("string")?;
+        let list = required("list")?;
+        let assign = required("assign")?;
+        let ident = match directives.remove("ident") {
+            Some(x) => Regex::new(&format!("^(?:{})$", x))
+                .with_context(|| format!("Invalid `ident` regex `{}`", x))?,
+            None => Regex::new("^[a-zA-Z_][a-zA-Z0-9_]*$").unwrap(),
+        };
+        Ok(Self {
+            string,
+            escapes,
+            control: directives.remove("control"),
+            byte: directives.remove("byte"),
+            list,
+            separator: directives.remove("separator").unwrap_or_else(|| " ".into()),
+            assign,
+            local_assign: directives.remove("local-assign"),
+            unset: directives.remove("unset"),
+            true_value: directives.remove("true"),
+            false_value: directives.remove("false"),
+            ident,
+            var_prefix: directives.remove("var-prefix"),
+        })
+    }
+
+    // The prefix of the variables when none is passed to `parse`.
+    pub(crate) fn var_prefix(&self) -> Option<&str> {
+        self.var_prefix.as_deref()
+    }
+
+    // The regex of the variable names.
+    pub(crate) fn ident(&self) -> &Regex {
+        &self.ident
+    }
+
+    fn escape_str(&self, s: &str, escaped: &mut String) {
+        for x in s.chars() {
+            match (self.escapes.get(&x), &self.control) {
+                (Some(replacement), _) => escaped.push_str(replacement),
+                (None, Some(control)) if x.is_control() => {
+                    escaped.push_str(&code_point(control, u32::from(x)))
+                }
+                _ => escaped.push(x),
+            }
+        }
+    }
+
+    pub(crate) fn str_escape(&self, s: &str) -> String {
+        let mut escaped = String::new();
+        self.escape_str(s, &mut escaped);
+        render(&self.string, &[("str", &escaped)])
+    }
+
+    pub(crate) fn bytes_escape(&self, bytes: &[u8]) -> String {
+        let byte = match self.byte {
+            Some(ref x) => x,
+            None => return self.str_escape(&String::from_utf8_lossy(bytes)),
+        };
+        let mut escaped = String::new();
+        for (valid, invalid) in utf8_parts(bytes) {
+            self.escape_str(&valid, &mut escaped);
+            for x in invalid.iter() {
+                escaped.push_str(&code_point(byte, u32::from(*x)));
+            }
+        }
+        render(&self.string, &[("str", &escaped)])
+    }
+
+    pub(crate) fn list(&self, xs: &[String]) -> String {
+        render(&self.list, &[("items", &xs.join(&self.separator))])
+    }
+
+    pub(crate) fn boolean(&self, b: bool) -> String {
+        match (b, &self.true_value, &self.false_value) {
+            (true, Some(x), _) | (false, _, Some(x)) => x.clone(),
+            _ => self.str_escape(&b.to_string()),
+        }
+    }
+
+    // Without `local-assign` local variables are assigned like the others.
+    pub(crate) fn assignment(&self, name: &str, value: &str, local: bool) -> String {
+        let template = match self.local_assign {
+            Some(ref x) if local => x,
+            _ => &self.assign,
+        };
+        render(template, &[("name", name), ("value", value)])
+    }
+
+    pub(crate) fn unset(&self, names: &[String], local: bool) -> String {
+        names
+            .iter()
+            .map(|x| match self.unset {
+                Some(ref unset) => render(unset, &[("name", x)]),
+                None => self.assignment(x, &self.list(&[]), local),
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
@@ -0,0 +1,45 @@
+use clap::{App, ArgSettings};
+
+// How many values an argument can take, according to its definition.
+#[derive(Clone, Copy, PartialEq)]
+pub enum ValueKind {
+    // Takes no values.
+    Flag,
+    Single,
+    Multiple,
+}
+
+impl ValueKind {
+    // The kind of the argument named `name` defined in `app` (not in its subcommands).
+    pub fn of(app: &App, name: &str) -> Option<Self> {
+        if app.p.flags.iter().any(|x| x.b.name == name) {
+            return Some(Self::Flag);
+        }
+        if let Some(x) = app.p.opts.iter().find(|x| x.b.name == name) {
+            return Some(Self::from_settings(
+                x.b.is_set(ArgSettings::Multiple) || x.b.is_set(ArgSettings::UseValueDelimiter),
+                &[x.v.num_vals, x.v.max_vals, x.v.min_vals],
+            ));
+        }
+        if let Some(x) = app.p.positionals.values().find(|x| x.b.name == name) {
+            return Some(Self::from_settings(
+                x.b.is_set(ArgSettings::Multiple) || x.b.is_set(ArgSettings::UseValueDelimiter),
+                &[x.v.num_vals, x.v.max_vals, x.v.min_vals],
+            ));
+        }
+        None
+    }
+
+    fn from_settings(multiple: bool, num_vals: &[Option<u64>]) -> Self {
+        if multiple || num_vals.iter().any(|x| x.is_some_and(|x| x > 1)) {
+            Self::Multiple
+        } else {
+            Self::Single
+        }
+    }
+
+    // Names of the flags defined in `app` (not in its subcommands).
+    pub fn flags<'a>(app: &'a App) -> impl Iterator<Item = &'a str> {
+        app.p.flags.iter().map(|x| x.b.name)
+    }
+}
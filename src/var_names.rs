@@ -0,0 +1,223 @@
+use {anyhow::bail, clap::App, std::collections::HashMap};
+
+/// The part of a variable name that tells what the variable contains.
+#[derive(Clone, Copy)]
+pub enum Suffix {
+    Occurs,
+    Indices,
+    Vals,
+    ValsCount,
+}
+
+/// How the names of the generated variables are built.
+#[derive(Clone, PartialEq)]
+pub struct VarNames {
+    /// `{prefix}` is replaced with the variable prefix, `{path}` with the subcommand path
+    /// followed by `separator` (empty outside of subcommands) and `{arg}` with the name of the
+    /// argument, or `success`, `usage` and `subcommand` for the other variables.
+    pub template: String,
+    /// Joins the subcommands of the path and the suffix to the rest of the name.
+    pub separator: String,
+    /// Upper-case the whole name.
+    pub upper: bool,
+    pub occurs_suffix: String,
+    pub indices_suffix: String,
+    pub vals_suffix: String,
+    pub vals_count_suffix: String,
+}
+
+impl Default for VarNames {
+    fn default() -> Self {
+        Self {
+            template: "{prefix}{path}{arg}".into(),
+            separator: "_".into(),
+            upper: false,
+            occurs_suffix: "occurs".into(),
+            indices_suffix: "indices".into(),
+            vals_suffix: "vals".into(),
+            vals_count_suffix: "vals_count".into(),
+        }
+    }
+}
+
+impl VarNames {
+    pub fn check(&self) -> anyhow::Result<()> {
+        if !self.template.contains("{arg}") {
+            bail!("The variable template must contain `{{arg}}`");
+        }
+        self.render("", "", "")?;
+        Ok(())
+    }
+
+    // Replaces the placeholders of the template in a single pass, so placeholders contained in
+    // the replacements aren't replaced.
+    fn render(&self, prefix: &str, path: &str, arg: &str) -> anyhow::Result<String> {
+        let mut rendered = String::new();
+        let mut rest = self.template.as_str();
+        while let Some(start) = rest.find('{') {
+            rendered.push_str(&rest[..start]);
+            rest = &rest[start..];
+            let end = match rest.find('}') {
+                Some(x) => x,
+                None => bail!(
+                    "Unclosed placeholder in variable template `{}`",
+                    self.template
+                ),
+            };
+            match &rest[..=end] {
+                "{prefix}" => rendered.push_str(prefix),
+                "{path}" => rendered.push_str(path),
+                "{arg}" => rendered.push_str(arg),
+                x => bail!(
+                    "Unknown placeholder `{}` in variable template `{}`, only `{{prefix}}`, `{{path}}` and `{{arg}}` are supported",
+                    x,
+                    self.template
+                ),
+            }
+            rest = &rest[end + 1..];
+        }
+        rendered.push_str(rest);
+        Ok(rendered)
+    }
+
+    pub fn name(
+        &self,
+        prefix: &str,
+        path: &[&str],
+        arg: &str,
+        suffix: Option<Suffix>,
+    ) -> anyhow::Result<String> {
+        let path = if path.is_empty() {
+            String::new()
+        } else {
+            format!("{}{}", path.join(&self.separator), self.separator)
+        };
+        let mut name = self.render(prefix, &path, arg)?;
+
+        let suffix = match suffix {
+            Some(Suffix::Occurs) => &self.occurs_suffix,
+            Some(Suffix::Indices) => &self.indices_suffix,
+            Some(Suffix::Vals) => &self.vals_suffix,
+            Some(Suffix::ValsCount) => &self.vals_count_suffix,
+            None => "",
+        };
+        if !suffix.is_empty() {
+            name.push_str(&self.separator);
+            name.push_str(suffix);
+        }
+
+        Ok(if self.upper {
+            name.to_uppercase()
+        } else {
+            name
+        })
+    }
+
+    /// Checks that no two variables that can be generated from `app` have the same name,
+    /// whatever arguments are parsed, and returns the names. For example a subcommand `a_b`
+    /// and a subcommand `b` of `a` collide with the default names.
+    pub(crate) fn check_collisions(&self, app: &App, prefix: &str) -> anyhow::Result<Vec<String>> {
+        let mut collisions = Collisions {
+            names: self,
+            prefix,
+            vars: HashMap::new(),
+            order: Vec::new(),
+        };
+        collisions.add(&[], "success", None, || "the success variable".into())?;
+        collisions.app(app, &mut Vec::new())?;
+        Ok(collisions.order)
+    }
+}
+
+// Every variable that can be generated from an app, see `VarNames::check_collisions`.
+struct Collisions<'a> {
+    names: &'a VarNames,
+    prefix: &'a str,
+    // The name of every variable and a description of what it is generated from.
+    vars: HashMap<String, String>,
+    // The names in the order they were found, for a stable output.
+    order: Vec<String>,
+}
+
+impl<'a> Collisions<'a> {
+    fn add(
+        &mut self,
+        path: &[&str],
+        arg: &str,
+        suffix: Option<Suffix>,
+        describe: impl FnOnce() -> String,
+    ) -> anyhow::Result<()> {
+        let name = self.names.name(self.prefix, path, arg, suffix)?;
+        let source = describe();
+        if let Some(other) = self.vars.get(&name) {
+            bail!(
+                "The variables of {} and {} would have the same name `{}`",
+                other,
+                source,
+                name
+            );
+        }
+        self.vars.insert(name.clone(), source);
+        self.order.push(name);
+        Ok(())
+    }
+
+    fn app<'b>(&mut self, app: &'b App, path: &mut Vec<&'b str>) -> anyhow::Result<()> {
+        let subcommand_desc = |path: &[&str]| {
+            if path.is_empty() {
+                String::new()
+            } else {
+                format!(" of subcommand '{}'", path.join(" "))
+            }
+        };
+        // The help subcommand and the help and version flags are added by slap, they never
+        // generate variables.
+        let subcommands = app
+            .p
+            .subcommands
+            .iter()
+            .filter(|x| x.get_name() != "help")
+            .collect::<Vec<_>>();
+        let args = app
+            .p
+            .flags
+            .iter()
+            .map(|x| x.b.name)
+            .chain(app.p.opts.iter().map(|x| x.b.name))
+            .chain(app.p.positionals.values().map(|x| x.b.name))
+            .filter(|x| *x != "help" && *x != "version");
+
+        self.add(path, "usage", None, || {
+            format!("the usage{}", subcommand_desc(path))
+        })?;
+        if !subcommands.is_empty() {
+            self.add(path, "subcommand", None, || {
+                format!("the subcommand name{}", subcommand_desc(path))
+            })?;
+        }
+        for name in args {
+            let suffixes = [
+                (Suffix::Occurs, "occurrences"),
+                (Suffix::Indices, "indices"),
+                (Suffix::Vals, "values"),
+                (Suffix::ValsCount, "values count"),
+            ];
+            for (suffix, what) in suffixes.iter() {
+                self.add(path, name, Some(*suffix), || {
+                    format!(
+                        "the {} of argument '{}'{}",
+                        what,
+                        name,
+                        subcommand_desc(path)
+                    )
+                })?;
+            }
+        }
+        for subcommand in subcommands {
+            path.push(subcommand.get_name());
+            self.app(subcommand, path)?;
+            path.pop();
+        }
+        Ok(())
+    }
+}
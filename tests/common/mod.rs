@@ -0,0 +1,249 @@
+//! The shells that evaluate the code generated by slap in the tests, and the `slap` binary.
+//!
+//! bash and python3 must be installed. The tests of the other shells are ignored, they run with
+//! `cargo test -- --ignored` and fail if their shell isn't installed.
+#![allow(dead_code)]
+
+use {
+    slap::{Shell, Template},
+    std::{
+        env, fs,
+        path::{Path, PathBuf},
+        process::{self, Command},
+        sync::atomic::{AtomicUsize, Ordering},
+    },
+};
+
+// The cache of the `slap` binaries spawned by the tests, instead of the one of the user.
+pub fn cache_home() -> PathBuf {
+    env::temp_dir().join(format!("slap-tests-cache-{}", process::id()))
+}
+
+// The `slap` binary, with `cache_home` as `$XDG_CACHE_HOME`.
+pub fn slap() -> Command {
+    let mut cmd = Command::new(env!("CARGO_BIN_EXE_slap"));
+    cmd.env("XDG_CACHE_HOME", cache_home());
+    cmd
+}
+
+// Printed after every value by `print_values` and `print_scalar`.
+pub const SEPARATOR: u8 = 0x1e;
+
+pub struct Target {
+    pub shell: Shell,
+    pub program: &'static str,
+    pub extension: &'static str,
+}
+
+pub const BASH: Target = Target {
+    shell: Shell::Bash,
+    program: "bash",
+    extension: "bash",
+};
+pub const ELVISH: Target = Target {
+    shell: Shell::Elvish,
+    program: "elvish",
+    extension: "elv",
+};
+pub const FISH: Target = Target {
+    shell: Shell::Fish,
+    program: "fish",
+    extension: "fish",
+};
+pub const KSH: Target = Target {
+    shell: Shell::Ksh,
+    program: "ksh",
+    extension: "ksh",
+};
+pub const MUREX: Target = Target {
+    shell: Shell::Murex,
+    program: "murex",
+    extension: "mx",
+};
+pub const PWSH: Target = Target {
+    shell: Shell::PowerShell,
+    program: "pwsh",
+    extension: "ps1",
+};
+pub const RC: Target = Target {
+    shell: Shell::Rc,
+    program: "rc",
+    extension: "rc",
+};
+pub const TCSH: Target = Target {
+    shell: Shell::Tcsh,
+    program: "tcsh",
+    extension: "csh",
+};
+pub const XONSH: Target = Target {
+    shell: Shell::Xonsh,
+    program: "xonsh",
+    extension: "xsh",
+};
+pub const YSH: Target = Target {
+    shell: Shell::Ysh,
+    program: "ysh",
+    extension: "ysh",
+};
+pub const ZSH: Target = Target {
+    shell: Shell::Zsh,
+    program: "zsh",
+    extension: "zsh",
+};
+
+// Python, evaluated by python3, exercises `slap parse --template`.
+#[rustfmt::skip]
+pub const PYTHON_TEMPLATE: &str = concat!(
+    "# Python 3\n",
+    "string '{str}'\n",
+    r"escape \\ \\", "\n",
+    r"escape ' \'", "\n",
+    r"control \x{hex}", "\n",
+    r"byte \udc{hex}", "\n",
+    "list [{items}]\n",
+    "separator , \n",
+    "assign {name} = {value}\n",
+    "true True\n",
+    "false False\n",
+);
+
+pub fn python_template() -> Target {
+    Target {
+        shell: Shell::Template(Box::new(Template::load(PYTHON_TEMPLATE).unwrap())),
+        program: "python3",
+        extension: "py",
+    }
+}
+
+pub const TARGETS: &[Target] = &[
+    BASH, ELVISH, FISH, KSH, MUREX, PWSH, RC, TCSH, XONSH, YSH, ZSH,
+];
+
+impl Target {
+    // Code that prints every value of the array `var`, each one followed by `SEPARATOR`.
+    pub fn print_values(&self, var: &str) -> String {
+        match self.shell {
+            Shell::Bash | Shell::Ksh | Shell::Zsh => format!(
+                "for v in \"${{{}[@]}}\"; do printf '%s\\036' \"$v\"; done",
+                var
+            ),
+            Shell::Elvish => format!("for v ${} {{ print $v\"\\x1e\" }}", var),
+            Shell::Fish => format!("for v ${}; printf '%s\\x1e' $v; end", var),
+            Shell::Murex => format!("${} -> foreach v {{ printf '%s\\036' $v }}", var),
+            Shell::PowerShell => format!(
+                "[Console]::OutputEncoding = [System.Text.UTF8Encoding]::new($false)\n\
+                 foreach ($v in (Get-Variable -Name '{}' -ValueOnly)) {{ \
+                 [Console]::Out.Write($v + [char]0x1e) }}",
+                var.replace('\'', "''")
+            ),
+            Shell::Rc => format!("for (v in ${}) printf '%s\\036' $v", var),
+            Shell::Tcsh => format!("foreach v ( ${}:q )\n    printf '%s\\036' $v:q\nend", var),
+            Shell::Template(_) | Shell::Xonsh => format!(
+                "import os, sys\n\
+                 for v in {}:\n    \
+                 sys.stdout.buffer.write(os.fsencode(v) + b'\\x1e')",
+                var
+            ),
+            Shell::Ysh => format!("for v in ({}) {{ write --end u'\\u{{1e}}' -- $v }}", var),
+        }
+    }
+
+    // Code that prints the scalar `var`, followed by `SEPARATOR`.
+    pub fn print_scalar(&self, var: &str) -> String {
+        match self.shell {
+            Shell::Bash | Shell::Ksh | Shell::Zsh => format!("printf '%s\\036' \"${}\"", var),
+            Shell::Elvish => format!("print ${}\"\\x1e\"", var),
+            Shell::Fish => format!("printf '%s\\x1e' ${}", var),
+            Shell::Murex | Shell::Rc => format!("printf '%s\\036' ${}", var),
+            Shell::PowerShell => format!(
+                "[Console]::Out.Write((Get-Variable -Name '{}' -ValueOnly) + [char]0x1e)",
+                var.replace('\'', "''")
+            ),
+            Shell::Tcsh => format!("printf '%s\\036' ${}:q", var),
+            Shell::Template(_) | Shell::Xonsh => format!("print({}, end='\\x1e')", var),
+            Shell::Ysh => format!("write --end u'\\u{{1e}}' -- $[{}]", var),
+        }
+    }
+
+    // Runs `code` and returns its stdout.
+    pub fn run(&self, code: &str) -> Vec<u8> {
+        assert!(
+            which::which(self.program).is_ok(),
+            "{} isn't installed",
+            self.program
+        );
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        let path = env::temp_dir().join(format!(
+            "slap-escaping-{}-{}.{}",
+            process::id(),
+            COUNTER.fetch_add(1, Ordering::SeqCst),
+            self.extension
+        ));
+        fs::write(&path, code).unwrap();
+        let output = self.command(&path).output().unwrap();
+        fs::remove_file(&path).unwrap();
+        assert!(
+            output.status.success(),
+            "{} failed evaluating:\n{}\nstderr:\n{}",
+            self.program,
+            code,
+            String::from_utf8_lossy(&output.stderr)
+        );
+        output.stdout
+    }
+
+    fn command(&self, path: &Path) -> Command {
+        let mut cmd = Command::new(self.program);
+        match self.shell {
+            Shell::PowerShell => {
+                cmd.args(["-NoProfile", "-NonInteractive", "-File"]);
+            }
+            Shell::Tcsh | Shell::Zsh => {
+                cmd.arg("-f");
+            }
+            Shell::Xonsh => {
+                cmd.arg("--no-rc");
+            }
+            _ => {}
+        }
+        cmd.arg(path);
+        cmd
+    }
+}
+
+// Defines a test running `$test(target: &Target)` for every target of `TARGETS`, in a module
+// named after its shell.
+#[allow(unused_macros)]
+macro_rules! target_tests {
+    ( $( $test:ident ),* $(,)? ) => {
+        target_tests! {
+            @targets [$($test),*]
+            bash => BASH, [];
+            elvish => ELVISH, [ignore = "needs elvish"];
+            fish => FISH, [ignore = "needs fish"];
+            ksh => KSH, [ignore = "needs ksh"];
+            murex => MUREX, [ignore = "needs murex"];
+            pwsh => PWSH, [ignore = "needs pwsh"];
+            rc => RC, [ignore = "needs rc"];
+            tcsh => TCSH, [ignore = "needs tcsh"];
+            xonsh => XONSH, [ignore = "needs xonsh"];
+            ysh => YSH, [ignore = "needs ysh"];
+            zsh => ZSH, [ignore = "needs zsh"];
+        }
+    };
+    ( @targets $tests:tt $( $name:ident => $target:ident, [$($attr:meta)?]; )* ) => {
+        $( target_tests! { @target $tests $name $target [$($attr)?] } )*
+    };
+    ( @target [$($test:ident),*] $name:ident $target:ident $attrs:tt ) => {
+        mod $name {
+            $( target_tests! { @test $attrs $target $test } )*
+        }
+    };
+    ( @test [$($attr:meta)?] $target:ident $test:ident ) => {
+        #[test]
+        $(#[$attr])?
+        fn $test() {
+            super::$test(&crate::common::$target);
+        }
+    };
+}
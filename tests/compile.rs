@@ -0,0 +1,280 @@
+//! Tests for the scripts generated by `slap compile`, that must set the same variables as the
+//! code generated by `slap parse`.
+//!
+//! The scripts are run by bash, the tests that need it are skipped if it isn't installed.
+
+use {
+    slap::{Error, Shell},
+    std::{ffi::OsString, process::Command},
+};
+
+const CONFIG: &str = "name: compiled\n\
+                      version: \"1.0\"\n\
+                      args:\n  \
+                        - verbose:\n      \
+                            short: v\n      \
+                            long: verbose\n      \
+                            multiple: true\n  \
+                        - level:\n      \
+                            short: l\n      \
+                            long: level\n      \
+                            takes_value: true\n      \
+                            possible_values: [low, high]\n  \
+                        - count:\n      \
+                            short: c\n      \
+                            takes_value: true\n      \
+                            multiple: true\n      \
+                            type: int\n  \
+                        - pattern:\n      \
+                            short: e\n      \
+                            takes_value: true\n      \
+                            allow_hyphen_values: true\n  \
+                        - input:\n      \
+                            index: 1\n      \
+                            required: true\n  \
+                        - rest:\n      \
+                            index: 2\n      \
+                            multiple: true\n\
+                      subcommands:\n  \
+                        - run:\n      \
+                            args:\n        \
+                              - dry:\n            \
+                                  long: dry\n            \
+                                  takes_value: true\n            \
+                                  type: bool\n      \
+                            subcommands:\n        \
+                              - now:\n            \
+                                  args:\n              \
+                                    - force:\n                  \
+                                        short: f\n";
+
+fn has_bash() -> bool {
+    Command::new("bash").arg("-c").arg(":").output().is_ok()
+}
+
+// Sources `script` with `args` in bash with `set -eu`, then prints the variables of the `_c_`
+// prefix and the leftovers of the script. Returns the exit code, stdout and stderr.
+fn source(script: &str, args: &[&str]) -> (i32, String, String) {
+    let output = Command::new("bash")
+        .arg("-c")
+        .arg(format!(
+            "set -eu\n{}\ndeclare -p $(compgen -v _c_)\ncompgen -v __slap_ || true\ncompgen -A function __slap_ || true",
+            script
+        ))
+        .arg("compiled")
+        .args(args)
+        .output()
+        .unwrap();
+    (
+        output.status.code().unwrap(),
+        String::from_utf8(output.stdout).unwrap(),
+        String::from_utf8(output.stderr).unwrap(),
+    )
+}
+
+fn compiled(config: &str, args: &[&str]) -> (i32, String, String) {
+    let script = slap::compile(config, &Shell::Bash, Some("_c_")).unwrap();
+    source(
+        &format!(
+            "source /dev/stdin \"$@\" <<'__SCRIPT__'\n{}\n__SCRIPT__",
+            script
+        ),
+        args,
+    )
+}
+
+fn parse(config: &str, args: &[&str]) -> Result<String, Error> {
+    let args = args.iter().map(OsString::from).collect::<Vec<_>>();
+    slap::parse(config, &Shell::Bash, &args, Some("_c_"))
+}
+
+// Checks that the compiled script behaves like `slap parse`: it sets the same variables, or prints
+// the same message with the same exit code.
+fn assert_same_as_parse(config: &str, args: &[&str]) {
+    let (status, stdout, stderr) = compiled(config, args);
+    match parse(config, args) {
+        Ok(code) => {
+            let expected = source(&code, &[]);
+            assert_eq!(
+                (status, stdout.as_str(), stderr.as_str()),
+                (0, expected.1.as_str(), ""),
+                "args: {:?}",
+                args
+            );
+        }
+        Err(Error::Help(msg)) | Err(Error::Version(msg)) => {
+            assert_eq!(
+                (status, stdout, stderr),
+                (0, format!("{}\n", msg), String::new()),
+                "args: {:?}",
+                args
+            );
+        }
+        // The help of nested subcommands is displayed by clap itself.
+        Err(Error::Args(e)) if !e.use_stderr() => {
+            assert_eq!(
+                (status, stdout, stderr),
+                (0, format!("{}\n", e.message), String::new()),
+                "args: {:?}",
+                args
+            );
+        }
+        Err(Error::Args(e)) => {
+            assert_eq!((status, stdout.as_str()), (1, ""), "args: {:?}", args);
+            assert_eq!(stderr, format!("{}\n", e.message), "args: {:?}", args);
+        }
+        Err(e) => panic!("unexpected error for {:?}: {}", args, e),
+    }
+}
+
+#[test]
+fn same_variables_as_parse() {
+    if !has_bash() {
+        return;
+    }
+    let cases: &[&[&str]] = &[
+        &["in"],
+        &["in", "a", "b c", "it's"],
+        &["-vvv", "in", "-v"],
+        &["--level", "low", "in"],
+        &["--level=high", "in"],
+        &["in", "-c", "1", "007", "-c-5"],
+        &["-c", "+3", "--", "in", "-x", "--"],
+        &["-e", "-x", "in"],
+        &["-e", "--", "in"],
+        &["-ve=x", "in", "run"],
+        &["in", "run", "--dry", "yes", "now", "-f"],
+        &["in", "run", "--dry=Off"],
+        &["in", "a", "-v", "b"],
+        &["-", "--", "-"],
+        &["in", "rnu", "now"],
+        // Errors and messages, that don't set any variable.
+        &["-c", "x", "in"],
+        &["-l", "low", "-l", "high", "in"],
+        &["in", "run", "now", "-ff"],
+        &["--help"],
+        &["-V", "-x"],
+        &["in", "help", "run"],
+    ];
+    for args in cases {
+        assert_same_as_parse(CONFIG, args);
+    }
+}
+
+#[test]
+fn same_errors_as_parse() {
+    if !has_bash() {
+        return;
+    }
+    let cases: &[&[&str]] = &[
+        &[],
+        &["-x"],
+        &["--nope=1", "in"],
+        &["--level", "mid", "in"],
+        &["-l"],
+        &["-l", "low", "-l", "high", "in"],
+        &["-c", "x", "in"],
+        &["-c", "9223372036854775808", "in"],
+        &["in", "run", "--dry", "maybe"],
+        &["in", "run", "now", "-ff"],
+        &["in", "run", "extra"],
+        &["in", "run", "--", "now"],
+        &["in", "runn"],
+        &["hepl"],
+        &["-vv", "-l", "low", "-l", "low"],
+        &["--help", "-l"],
+        &["--level", "hihg", "in"],
+        &["--levl", "low", "in"],
+        &["--dyr", "yes", "in", "run"],
+        &["in", "run", "--dyr"],
+        &["in", "run", "now", "--forc"],
+    ];
+    for args in cases {
+        assert!(
+            matches!(parse(CONFIG, args), Err(Error::Args(ref e)) if e.use_stderr()),
+            "expected an error for {:?}",
+            args
+        );
+        assert_same_as_parse(CONFIG, args);
+    }
+}
+
+// Like clap, the usage of the errors lists the required arguments and the ones that were used.
+#[test]
+fn usage_of_errors() {
+    if !has_bash() {
+        return;
+    }
+    let config = "name: t\n\
+                  args:\n  \
+                    - out:\n      \
+                        short: o\n      \
+                        long: out\n      \
+                        takes_value: true\n      \
+                        required: true\n  \
+                    - quiet:\n      \
+                        short: q\n      \
+                        long: quiet\n";
+    let cases: &[&[&str]] = &[
+        &["-o", "a", "-q", "-q"],
+        &["--help"],
+        &["-q"],
+        &["-x"],
+        &["-o", "a", "--quite"],
+        &["--otu", "a"],
+    ];
+    for args in cases {
+        assert_same_as_parse(config, args);
+    }
+    let (_, _, stderr) = compiled(config, &["-o", "a", "-q", "-q"]);
+    assert!(
+        stderr.contains("\nUSAGE:\n    t --out <out> --quiet\n"),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+fn help_and_version_messages() {
+    if !has_bash() {
+        return;
+    }
+    let cases: &[&[&str]] = &[
+        &["--help", "in"],
+        &["-V", "in"],
+        &["in", "help"],
+        &["in", "help", "run"],
+        &["in", "run", "-h"],
+        &["in", "run", "now", "--help"],
+    ];
+    for args in cases {
+        let message = match parse(CONFIG, args) {
+            Err(Error::Help(_)) | Err(Error::Version(_)) => true,
+            Err(Error::Args(e)) => !e.use_stderr(),
+            _ => false,
+        };
+        assert!(message, "expected a message for {:?}", args);
+        assert_same_as_parse(CONFIG, args);
+    }
+}
+
+#[test]
+fn unsupported_keys() {
+    let config = "name: compiled\n\
+                  args:\n  \
+                    - a:\n      \
+                        long: a\n      \
+                        takes_value: true\n      \
+                        default_value: x\n";
+    match slap::compile(config, &Shell::Bash, None) {
+        Err(Error::Config(e)) => assert_eq!(
+            e.to_string(),
+            "`slap compile` doesn't support `default_value`, used by argument 'a' of the app"
+        ),
+        x => panic!(
+            "expected a config error, got {:?}",
+            x.map_err(|e| e.to_string())
+        ),
+    }
+    assert!(slap::compile(CONFIG, &Shell::Zsh, None).is_err());
+}
@@ -0,0 +1,276 @@
+//! Tests for the completions of the values of arguments with a `complete_command` or a
+//! `value_hint`.
+
+mod common;
+
+use {
+    common::{cache_home, slap},
+    slap::{Config, Error, Shell},
+    std::{env, path::Path, process::Command},
+};
+
+const CONFIG: &str = "name: deploy\n\
+                      args:\n  \
+                        - verbose:\n      \
+                            short: v\n  \
+                        - env:\n      \
+                            short: e\n      \
+                            long: env\n      \
+                            takes_value: true\n      \
+                            complete_command: printf '%s\\n' prod staging dev\n  \
+                        - service:\n      \
+                            index: 1\n      \
+                            complete_command: echo web; echo worker\n\
+                      subcommands:\n  \
+                        - rollback:\n      \
+                            args:\n        \
+                              - release:\n            \
+                                  index: 1\n            \
+                                  complete_command: echo r1; echo 'r*'\n        \
+                              - log:\n            \
+                                  long: log\n            \
+                                  takes_value: true\n            \
+                                  value_hint: file\n  \
+                        - copy:\n      \
+                            args:\n        \
+                              - source:\n            \
+                                  index: 1\n            \
+                                  value_hint: file\n        \
+                              - dest:\n            \
+                                  index: 2\n            \
+                                  value_hint: dir\n";
+
+fn words(words: &[&str]) -> Vec<String> {
+    words.iter().map(|x| x.to_string()).collect()
+}
+
+fn complete_command(words: &[&str]) -> Option<String> {
+    let config = Config::load(CONFIG).unwrap();
+    let words = self::words(words);
+    config
+        .app()
+        .unwrap()
+        .complete_command(&words)
+        .map(String::from)
+}
+
+#[test]
+fn finds_the_argument_being_completed() {
+    let env = Some("printf '%s\\n' prod staging dev");
+    let service = Some("echo web; echo worker");
+    let cases: &[(&[&str], Option<&str>)] = &[
+        (&[""], service),
+        (&["--env", "st"], env),
+        (&["-ve", ""], env),
+        (&["-e", "prod", ""], service),
+        (&["-eprod", ""], service),
+        (&["--env=prod", ""], service),
+        // Bash splits `--env=pr` at the `=`.
+        (&["--env", "=", "pr"], env),
+        (&["--env", "="], env),
+        (&["--env", "=", "prod", ""], service),
+        (&["-v", "="], service),
+        (&["-"], None),
+        (&["web", ""], None),
+        (&["--", "-x"], service),
+        (&["rollback", ""], Some("echo r1; echo 'r*'")),
+        (&["rollback", "-h"], None),
+    ];
+    for (words, expected) in cases {
+        assert_eq!(
+            complete_command(words).as_deref(),
+            *expected,
+            "words: {:?}",
+            words
+        );
+    }
+}
+
+#[test]
+fn finds_the_value_hint() {
+    let config = Config::load(CONFIG).unwrap();
+    let app = config.app().unwrap();
+    assert_eq!(
+        app.value_hint(&words(&["rollback", "--log", ""])),
+        Some("file")
+    );
+    assert_eq!(app.value_hint(&words(&["rollback", ""])), None);
+    assert_eq!(
+        app.complete_command(&words(&["rollback", "--log", ""])),
+        None
+    );
+}
+
+#[test]
+fn invalid_keys() {
+    let cases = [
+        (
+            "short: v\n      complete_command: echo x\n",
+            "`complete_command` can't be used on argument 'verbose' because it doesn't take values",
+        ),
+        (
+            "short: v\n      value_hint: dir\n",
+            "`value_hint` can't be used on argument 'verbose' because it doesn't take values",
+        ),
+        (
+            "index: 1\n      value_hint: folder\n",
+            "Invalid argument 'verbose': `value_hint` must be one of \
+             [\"command\", \"dir\", \"file\", \"hostname\", \"url\", \"user\"]",
+        ),
+        (
+            "index: 1\n      complete_command: echo x\n      value_hint: dir\n",
+            "Invalid argument 'verbose': `value_hint` and `complete_command` can't be used together",
+        ),
+    ];
+    for (arg, expected) in cases {
+        let config = format!("name: deploy\nargs:\n  - verbose:\n      {}", arg);
+        let error = match Config::load(&config).and_then(|x| x.app().map(|_| ())) {
+            Err(Error::Config(e)) => e,
+            _ => panic!("expected a config error for {:?}", arg),
+        };
+        assert_eq!(format!("{:#}", error), expected);
+    }
+}
+
+#[test]
+fn bash_script_completes_the_values() {
+    if Command::new("bash").arg("-c").arg(":").output().is_err() {
+        return;
+    }
+    let script = slap::completions(CONFIG, &Shell::Bash).unwrap();
+    let bin_dir = Path::new(env!("CARGO_BIN_EXE_slap")).parent().unwrap();
+    let path = env::join_paths(
+        std::iter::once(bin_dir.to_path_buf())
+            .chain(env::split_paths(&env::var_os("PATH").unwrap_or_default())),
+    )
+    .unwrap();
+    let complete_with_path = |words: &str, path: &std::ffi::OsStr| {
+        let output = Command::new("bash")
+            .arg("-c")
+            .arg(format!(
+                "{}\nCOMP_WORDS=({}); COMP_CWORD=$((${{#COMP_WORDS[@]}} - 1))\n\
+                 _deploy_slap_complete\nprintf '%s\\n' \"${{COMPREPLY[@]}}\"",
+                script, words
+            ))
+            .env("PATH", path)
+            .env("XDG_CACHE_HOME", cache_home())
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{:?}", output);
+        String::from_utf8(output.stdout).unwrap()
+    };
+    let complete = |words: &str| complete_with_path(words, &path);
+    assert_eq!(complete("deploy --env s"), "staging\n");
+    assert_eq!(complete("deploy --env = s"), "staging\n");
+    assert_eq!(complete("deploy --env ="), "prod\nstaging\ndev\n");
+    // The candidates aren't globs, `r*` isn't expanded to the files of the directory.
+    assert_eq!(complete("deploy rollback ''"), "r1\nr*\n");
+    // Falls back to the completions of clap.
+    assert_eq!(complete("deploy --ver"), "--version\n");
+
+    // The value hints are completed by compgen, without `slap complete`.
+    let dir = env!("CARGO_MANIFEST_DIR");
+    let path = env::var_os("PATH").unwrap_or_default();
+    let complete = |words: &str| complete_with_path(words, &path);
+    let cases = [
+        ("deploy rollback --log '{}/src/lib.r'", "{}/src/lib.rs\n"),
+        ("deploy rollback --log = '{}/src/lib.r'", "{}/src/lib.rs\n"),
+        ("deploy copy '{}/Cargo.t'", "{}/Cargo.toml\n"),
+        ("deploy -e prod copy -- '{}/Cargo.t'", "{}/Cargo.toml\n"),
+        ("deploy copy x '{}/tes'", "{}/tests\n"),
+        ("deploy copy x '{}/Cargo.t'", "\n"),
+    ];
+    for (words, expected) in cases {
+        assert_eq!(
+            complete(&words.replace("{}", dir)),
+            expected.replace("{}", dir),
+            "words: {}",
+            words
+        );
+    }
+}
+
+#[test]
+fn zsh_and_fish_scripts_complete_the_values() {
+    let zsh = slap::completions(CONFIG, &Shell::Zsh).unwrap();
+    for spec in [
+        "'-e+[]: :{_deploy_slap_values}' \\",
+        "'--env=[]: :{_deploy_slap_values}' \\",
+        "'::service:{_deploy_slap_values}' \\",
+        "'::release:{_deploy_slap_values '\\''rollback'\\''}' \\",
+        "'--log=[]: :_files' \\",
+        "'::source:_files' \\",
+        "'::dest:_files -/' \\",
+    ] {
+        assert!(zsh.lines().any(|x| x == spec), "{}\n{}", spec, zsh);
+    }
+    assert!(zsh.ends_with("\n_deploy \"$@\""));
+
+    let fish = slap::completions(CONFIG, &Shell::Fish).unwrap();
+    for line in [
+        "    set -l opts '/-e' '/--env' 'rollback/--log'",
+        "    set -l opt_records '00values' '00values' '00file'",
+        "    set -l positional_records '0values' '0-' '0values' '0file' '0dir'",
+    ] {
+        assert!(fish.lines().any(|x| x == line), "{}\n{}", line, fish);
+    }
+}
+
+#[test]
+fn every_shell_completes_the_hints() {
+    let hints = "name: deploy\n\
+                 args:\n  \
+                   - site:\n      \
+                       long: site\n      \
+                       takes_value: true\n      \
+                       value_hint: url\n  \
+                   - log:\n      \
+                       long: log\n      \
+                       takes_value: true\n      \
+                       value_hint: file\n";
+    // The shells that can't complete a hint or a `complete_command` fall back to the
+    // completions of clap.
+    for config in [hints, CONFIG] {
+        for shell in [
+            Shell::Bash,
+            Shell::Elvish,
+            Shell::Fish,
+            Shell::PowerShell,
+            Shell::Zsh,
+        ] {
+            let script = slap::completions(config, &shell).unwrap();
+            assert!(script.contains("deploy"), "{}", script);
+        }
+    }
+    assert!(slap::completions(hints, &Shell::Zsh)
+        .unwrap()
+        .lines()
+        .any(|x| x == "'--site=[]: :_urls' \\"));
+}
+
+#[cfg(unix)]
+#[test]
+fn complete_subcommand_takes_invalid_utf8() {
+    use std::{ffi::OsStr, io::Write, os::unix::ffi::OsStrExt, process::Stdio};
+
+    let mut child = slap()
+        .args(["complete", "--"])
+        .arg(OsStr::from_bytes(b"caf\xe9"))
+        .args(["--env", "s"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(CONFIG.as_bytes())
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "values\nstaging\n"
+    );
+}
@@ -0,0 +1,53 @@
+//! Tests for the number of values of the arguments, in particular zero.
+//!
+//! The generated code is evaluated by the real shells, see `common` for the ones that must be
+//! installed.
+
+#[macro_use]
+mod common;
+
+use {common::*, std::ffi::OsString};
+
+target_tests! {
+    zero_one_many_values,
+}
+
+fn zero_one_many_values(target: &Target) {
+    let config = "name: counts\n\
+                  args:\n  \
+                    - flag:\n      \
+                        short: f\n  \
+                    - opt:\n      \
+                        long: opt\n      \
+                        multiple: true\n      \
+                        takes_value: true\n";
+    for (args, expected) in &[
+        (vec!["-f"], "0\x1e"),
+        (vec!["-f", "--opt", "a"], "0\x1e1\x1ea\x1e"),
+        (
+            vec!["-f", "--opt", "a", "--opt", "b"],
+            "0\x1e2\x1ea\x1eb\x1e",
+        ),
+    ] {
+        let args = args.iter().map(OsString::from).collect::<Vec<_>>();
+        let code = slap::parse(config, &target.shell, &args, Some("_")).unwrap();
+        let mut code = format!(
+            "{}\n{}\n{}\n",
+            code,
+            target.print_values("_flag_vals"),
+            target.print_scalar("_flag_vals_count"),
+        );
+        if args.len() > 1 {
+            code.push_str(&target.print_scalar("_opt_vals_count"));
+            code.push('\n');
+            code.push_str(&target.print_values("_opt_vals"));
+            code.push('\n');
+        }
+        assert_eq!(
+            String::from_utf8(target.run(&code)).unwrap(),
+            *expected,
+            "generated code:\n{}",
+            code
+        );
+    }
+}
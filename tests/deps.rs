@@ -0,0 +1,64 @@
+//! Tests for the search path of `slap deps`.
+#![cfg(unix)]
+
+mod common;
+
+use {
+    common::slap,
+    std::{env, fs, path::Path},
+};
+
+fn slap_deps(dir: &Path, args: &[&str]) -> (bool, String) {
+    let output = slap()
+        .arg("deps")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .unwrap();
+    (
+        output.status.success(),
+        String::from_utf8(output.stderr).unwrap(),
+    )
+}
+
+#[test]
+fn names_where_dependencies_are_searched() {
+    let dir = env::temp_dir();
+    let cases: &[(&[&str], &str)] = &[
+        (&[], "$PATH"),
+        (&["--include-cwd"], "the current directory and $PATH"),
+        (&["--path", "/nonexistent"], "the given path list"),
+        (
+            &["--path", "/nonexistent", "--include-cwd"],
+            "the current directory and the given path list",
+        ),
+    ];
+    for (args, location) in cases {
+        let args = [&["slap-deps-tests-missing"], *args].concat();
+        let (success, stderr) = slap_deps(&dir, &args);
+        assert!(!success);
+        assert_eq!(
+            stderr,
+            format!(
+                "error: Required dependency slap-deps-tests-missing not found in {}\n",
+                location
+            )
+        );
+    }
+}
+
+#[test]
+fn rejects_directories_containing_the_separator() {
+    let dir = env::temp_dir().join(format!("slap-deps-tests-{}:x", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let (success, stderr) = slap_deps(&dir, &["sh", "--include-cwd"]);
+    assert!(!success);
+    assert_eq!(
+        stderr,
+        format!(
+            "error: Can't search dependencies in '{}', it contains the separator of path lists\n",
+            fs::canonicalize(&dir).unwrap().display()
+        )
+    );
+    fs::remove_dir_all(&dir).unwrap();
+}
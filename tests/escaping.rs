@@ -0,0 +1,175 @@
+//! Round-trip tests for the escaping of the code generated by slap.
+//!
+//! The generated code is evaluated by the real shells, see `common` for the ones that must be
+//! installed.
+
+#[macro_use]
+mod common;
+
+use {common::*, proptest::prelude::*, std::ffi::OsString};
+
+const CASES: u32 = 32;
+
+fn config(arg_name: &str) -> String {
+    format!(
+        "name: roundtrip\n\
+         args:\n  \
+           - \"{}\":\n      \
+               multiple: true\n      \
+               allow_hyphen_values: true\n      \
+               index: 1\n",
+        arg_name
+    )
+}
+
+// Parses `values` as the values of the `arg_name` positional argument, evaluates the generated
+// code and checks that the shell sees exactly `values`.
+fn roundtrip(target: &Target, arg_name: &str, values: &[OsString], expected: &[&[u8]]) {
+    let mut args = vec![OsString::from("--")];
+    args.extend(values.iter().cloned());
+    let code = slap::parse(&config(arg_name), &target.shell, &args, Some("_")).unwrap();
+    let var = format!("_{}_vals", arg_name);
+    let code = format!("{}\n{}\n", code, target.print_values(&var));
+
+    let mut expected_stdout = Vec::new();
+    for x in expected {
+        expected_stdout.extend_from_slice(x);
+        expected_stdout.push(SEPARATOR);
+    }
+    let stdout = target.run(&code);
+    assert_eq!(
+        String::from_utf8_lossy(&stdout),
+        String::from_utf8_lossy(&expected_stdout),
+        "generated code:\n{}",
+        code
+    );
+    assert_eq!(stdout, expected_stdout);
+}
+
+fn roundtrip_strings(target: &Target, arg_name: &str, values: &[String]) {
+    let args = values.iter().map(OsString::from).collect::<Vec<_>>();
+    let expected = values.iter().map(|x| x.as_bytes()).collect::<Vec<_>>();
+    roundtrip(target, arg_name, &args, &expected);
+}
+
+#[cfg(unix)]
+fn roundtrip_bytes(target: &Target, values: &[Vec<u8>]) {
+    use std::os::unix::ffi::OsStringExt;
+
+    let args = values
+        .iter()
+        .map(|x| OsString::from_vec(x.clone()))
+        .collect::<Vec<_>>();
+    let expected = values.iter().map(|x| x.as_slice()).collect::<Vec<_>>();
+    roundtrip(target, "vals", &args, &expected);
+}
+
+// Fragments that are meaningful to at least one of the shells.
+#[rustfmt::skip]
+const TRICKY: &[&str] = &[
+    "'", "''", "\"", "\\", "\\'", "\\\\", "\n", "\r\n", "\t", " ", "$", "$(echo x)", "${x}", "$x",
+    "`", "`echo x`", "*", "?", "[a]", "{a,b}", "~", "!", "!!", "#", ";", "&", "|", "<", ">", "(",
+    ")", "%", "@", "^", "-", "--", "-h", "=", "é", "ß", "🦀", "\u{2018}", "\u{2019}", "\u{201a}",
+    "\u{201b}", "\u{201c}", "\u{a0}",
+];
+
+fn value() -> impl Strategy<Value = String> {
+    let fragment = prop_oneof![
+        prop::sample::select(TRICKY).prop_map(String::from),
+        any::<char>()
+            .prop_filter("NUL and the separator can't be generated", |x| {
+                *x != '\0' && *x != SEPARATOR as char
+            })
+            .prop_map(String::from),
+    ];
+    prop::collection::vec(fragment, 0..8).prop_map(|xs| xs.concat())
+}
+
+fn values() -> impl Strategy<Value = Vec<String>> {
+    prop::collection::vec(value(), 1..6)
+}
+
+#[cfg(unix)]
+fn byte_values() -> impl Strategy<Value = Vec<Vec<u8>>> {
+    let byte = any::<u8>().prop_filter("NUL and the separator can't be generated", |x| {
+        *x != 0 && *x != SEPARATOR
+    });
+    prop::collection::vec(prop::collection::vec(byte, 0..12), 1..6)
+}
+
+// PowerShell accepts any variable name, exercising the quoting of `Set-Variable -Name`.
+fn pwsh_arg_name() -> impl Strategy<Value = String> {
+    "[a-zA-Z0-9 '$`(){}é;-]{0,8}".prop_map(|x| format!("a{}", x))
+}
+
+macro_rules! roundtrip_tests {
+    ( $( $(#[$attr:meta])* $name:ident, $bytes_name:ident => $target:expr; )* ) => {
+        $(
+            proptest! {
+                #![proptest_config(ProptestConfig::with_cases(CASES))]
+
+                #[test]
+                $(#[$attr])*
+                fn $name(values in values()) {
+                    roundtrip_strings(&$target, "vals", &values);
+                }
+
+                #[cfg(unix)]
+                #[test]
+                $(#[$attr])*
+                fn $bytes_name(values in byte_values()) {
+                    roundtrip_bytes(&$target, &values);
+                }
+            }
+        )*
+    };
+}
+
+roundtrip_tests! {
+    bash_roundtrip, bash_bytes_roundtrip => BASH;
+    #[ignore = "needs elvish"]
+    elvish_roundtrip, elvish_bytes_roundtrip => ELVISH;
+    #[ignore = "needs fish"]
+    fish_roundtrip, fish_bytes_roundtrip => FISH;
+    #[ignore = "needs ksh"]
+    ksh_roundtrip, ksh_bytes_roundtrip => KSH;
+    #[ignore = "needs murex"]
+    murex_roundtrip, murex_bytes_roundtrip => MUREX;
+    #[ignore = "needs rc"]
+    rc_roundtrip, rc_bytes_roundtrip => RC;
+    #[ignore = "needs tcsh"]
+    tcsh_roundtrip, tcsh_bytes_roundtrip => TCSH;
+    #[ignore = "needs xonsh"]
+    xonsh_roundtrip, xonsh_bytes_roundtrip => XONSH;
+    #[ignore = "needs ysh"]
+    ysh_roundtrip, ysh_bytes_roundtrip => YSH;
+    python_template_roundtrip, python_template_bytes_roundtrip => python_template();
+    #[ignore = "needs zsh"]
+    zsh_roundtrip, zsh_bytes_roundtrip => ZSH;
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(CASES))]
+
+    #[test]
+    #[ignore = "needs pwsh"]
+    fn pwsh_roundtrip(values in values()) {
+        roundtrip_strings(&PWSH, "vals", &values);
+    }
+
+    #[test]
+    #[ignore = "needs pwsh"]
+    fn pwsh_assignment_roundtrip(arg_name in pwsh_arg_name(), values in values()) {
+        roundtrip_strings(&PWSH, &arg_name, &values);
+    }
+}
+
+target_tests! {
+    tricky_fragments_roundtrip,
+}
+
+fn tricky_fragments_roundtrip(target: &Target) {
+    let values = TRICKY.iter().map(|x| x.to_string()).collect::<Vec<_>>();
+    roundtrip_strings(target, "vals", &values);
+    roundtrip_strings(target, "vals", &[String::new()]);
+}
@@ -0,0 +1,56 @@
+//! Tests for `--local` and `--reset`, that keep the variables of the generated code from leaking
+//! out of functions and from lingering after a previous parse.
+//!
+//! The generated code is evaluated by the real shells, see `common` for the ones that must be
+//! installed.
+
+mod common;
+
+use {common::*, std::ffi::OsString};
+
+#[test]
+fn bash_local_and_reset() {
+    local_and_reset(&BASH);
+}
+
+#[test]
+#[ignore = "needs zsh"]
+fn zsh_local_and_reset() {
+    local_and_reset(&ZSH);
+}
+
+fn local_and_reset(target: &Target) {
+    let config = "name: scoped\n\
+                  args:\n  \
+                    - n:\n      \
+                        long: n\n      \
+                        takes_value: true\n  \
+                    - v:\n      \
+                        long: v\n";
+    let parse = |target: &Target, args: &[&str], local: bool| {
+        let options = slap::ParseOptions {
+            local,
+            reset: true,
+            ..Default::default()
+        };
+        let args = args.iter().map(OsString::from).collect::<Vec<_>>();
+        slap::parse_with(config, &target.shell, &args, Some("_"), &options).unwrap()
+    };
+    // The stale `--v` of the first parse is unset by the second one, the local variables
+    // of `f` don't leak.
+    let code = format!(
+        "{}\n{}\nf() {{\n{}\n{}\n}}\nf\n{}\n{}\n",
+        parse(target, &["--n", "a", "--v"], false),
+        parse(target, &["--n", "b"], false),
+        parse(target, &["--n", "c"], true),
+        target.print_values("_n_vals"),
+        target.print_values("_n_vals"),
+        target.print_scalar("_v_occurs"),
+    );
+    assert_eq!(
+        String::from_utf8(target.run(&code)).unwrap(),
+        "c\x1eb\x1e\x1e",
+        "generated code:\n{}",
+        code
+    );
+}
@@ -0,0 +1,132 @@
+//! Tests for the cache of the configs, with their help and version messages.
+
+mod common;
+
+use {
+    common::slap,
+    slap::{Config, Error, Shell},
+    std::{
+        env,
+        ffi::OsString,
+        fs,
+        io::Write,
+        path::{Path, PathBuf},
+        process::{self, Stdio},
+    },
+};
+
+const CONFIG: &str = "name: cached\n\
+                      version: \"1.0\"\n\
+                      subcommands:\n  \
+                        - sub:\n      \
+                            about: A subcommand\n";
+
+fn files(dir: &Path) -> Vec<PathBuf> {
+    let mut files = fs::read_dir(dir)
+        .unwrap()
+        .map(|x| x.unwrap().path())
+        .collect::<Vec<_>>();
+    files.sort();
+    files
+}
+
+fn help(config: &Config, args: &[&str]) -> String {
+    let args = args.iter().map(OsString::from).collect::<Vec<_>>();
+    match config.app().unwrap().parse(&Shell::Bash, &args, None) {
+        Err(Error::Help(msg)) | Err(Error::Version(msg)) => msg,
+        x => panic!("expected a message, got {:?}", x.map_err(|e| e.to_string())),
+    }
+}
+
+#[test]
+fn messages_are_cached() {
+    let dir = env::temp_dir().join(format!("slap-message-cache-{}", process::id()));
+    let config = Config::load(CONFIG).unwrap().with_message_cache(&dir);
+    let uncached = Config::load(CONFIG).unwrap();
+
+    assert_eq!(
+        help(&config, &["sub", "--help"]),
+        help(&uncached, &["sub", "--help"])
+    );
+    assert_eq!(help(&config, &["--version"]), "cached 1.0");
+
+    // Later runs read the messages from the cache instead of rendering them.
+    let files = files(&dir);
+    assert_eq!(files.len(), 1);
+    let cached = fs::read_to_string(&files[0]).unwrap();
+    fs::write(&files[0], cached.replace("cached 1.0", "from the cache")).unwrap();
+    assert_eq!(help(&config, &["--version"]), "from the cache");
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn checks_are_cached() {
+    let dir = env::temp_dir().join(format!("slap-config-cache-{}", process::id()));
+    let colliding = "name: colliding\n\
+                     subcommands:\n  \
+                       - a_b:\n      \
+                           about: x\n  \
+                       - a:\n      \
+                           subcommands:\n        \
+                             - b:\n            \
+                                 about: y\n";
+    assert!(Config::load_cached(colliding, &dir).is_err());
+    assert!(!dir.exists());
+
+    Config::load_cached(CONFIG, &dir).unwrap();
+    let files = files(&dir);
+    assert_eq!(files.len(), 1);
+    assert_eq!(
+        fs::read_to_string(&files[0]).unwrap(),
+        r#"{"checked":true}"#
+    );
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn old_files_are_deleted() {
+    let dir = env::temp_dir().join(format!("slap-config-cache-prune-{}", process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    for i in 0..70 {
+        fs::write(dir.join(format!("old{:02}.json", i)), "{}").unwrap();
+    }
+    fs::write(dir.join("other"), "").unwrap();
+
+    Config::load_cached(CONFIG, &dir).unwrap();
+    let files = files(&dir);
+    assert_eq!(files.len(), 65);
+    assert!(files.iter().all(|x| !x.ends_with("old00.json")));
+    assert!(files.iter().any(|x| x.ends_with("other")));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn cache_can_be_disabled() {
+    let dir = env::temp_dir().join(format!("slap-config-cache-disabled-{}", process::id()));
+    let parse = |no_cache: &str| {
+        let mut child = slap()
+            .args(["parse", "bash"])
+            .env("XDG_CACHE_HOME", &dir)
+            .env("SLAP_NO_CACHE", no_cache)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .spawn()
+            .unwrap();
+        child
+            .stdin
+            .take()
+            .unwrap()
+            .write_all(CONFIG.as_bytes())
+            .unwrap();
+        assert!(child.wait().unwrap().success());
+    };
+    parse("1");
+    assert!(!dir.exists());
+    parse("");
+    assert_eq!(files(&dir.join("slap")).len(), 1);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
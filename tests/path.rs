@@ -0,0 +1,72 @@
+//! Tests for `slap path`, run in a directory tree containing symlinks.
+#![cfg(unix)]
+
+mod common;
+
+use {
+    common::slap,
+    std::{
+        env, fs,
+        os::unix::fs::symlink,
+        path::{Path, PathBuf},
+    },
+};
+
+// A tree of the tests `name`: `real/sub/t.sh`, `real/s.sh`, `lreal` linking to `real` and
+// `a/link` linking to `real/sub`.
+fn tree(name: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("slap-path-tests-{}-{}", std::process::id(), name));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(dir.join("real/sub")).unwrap();
+    fs::write(dir.join("real/s.sh"), "").unwrap();
+    fs::write(dir.join("real/sub/t.sh"), "").unwrap();
+    symlink("real", dir.join("lreal")).unwrap();
+    fs::create_dir(dir.join("a")).unwrap();
+    symlink("../real/sub", dir.join("a/link")).unwrap();
+    fs::canonicalize(dir).unwrap()
+}
+
+fn slap_path(dir: &Path, args: &[&str]) -> String {
+    let output = slap()
+        .arg("path")
+        .args(args)
+        .current_dir(dir)
+        .env("PWD", dir)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn parent_dir_after_symlink() {
+    let dir = tree("parent");
+    let path = |args: &[&str]| slap_path(&dir, args).replace(&*dir.to_string_lossy(), "");
+    // The `..` is applied to the target of the symlink, like `cd -P`.
+    assert_eq!(path(&["a/link/../s.sh"]), "/real/s.sh\n");
+    assert_eq!(path(&["-d", "a/link/../s.sh"]), "/real\n");
+    assert_eq!(path(&["a/link/./t.sh"]), "/real/sub/t.sh\n");
+    // Logically it removes the symlink instead.
+    assert_eq!(path(&["-L", "a/link/../s.sh"]), "/a/s.sh\n");
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn relative_to_resolves_both_sides() {
+    let dir = tree("relative");
+    for args in &[
+        &["lreal/sub/t.sh", "-r", "lreal"][..],
+        &["lreal/sub/t.sh", "-r", "real"],
+        &["real/sub/t.sh", "-r", "lreal"],
+        &["-c", "lreal/sub/t.sh", "-r", "real"],
+        &["-L", "lreal/sub/t.sh", "-r", "lreal"],
+        &["-L", "-c", "lreal/sub/t.sh", "-r", "lreal"],
+    ] {
+        assert_eq!(slap_path(&dir, args), "sub/t.sh\n", "args: {:?}", args);
+    }
+    assert_eq!(
+        slap_path(&dir, &["-L", "lreal/sub/t.sh", "-r", "real"]),
+        "../lreal/sub/t.sh\n"
+    );
+    fs::remove_dir_all(&dir).unwrap();
+}
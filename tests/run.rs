@@ -0,0 +1,160 @@
+//! Tests for the scripts run by `slap run`, that are skipped if bash isn't installed.
+
+mod common;
+
+use {
+    common::{cache_home, slap},
+    std::{
+        env, fs,
+        path::{Path, PathBuf},
+        process::{Command, Output},
+    },
+};
+
+const SCRIPT: &str = "#!/usr/bin/env slap-run\n\
+                      ---\n\
+                      shell: bash\n\
+                      var_prefix: _\n\
+                      name: greet\n\
+                      version: \"1.0\"\n\
+                      args:\n  \
+                        - loud:\n      \
+                            short: l\n  \
+                        - who:\n      \
+                            index: 1\n      \
+                            multiple: true\n\
+                      ---\n\
+                      printf '%s\\n' \"$0\" \"$*\" \"${_loud_occurs-0}\" \"${_who_vals[@]}\"\n";
+
+fn has_bash() -> bool {
+    Command::new("bash").arg("-c").arg(":").output().is_ok()
+}
+
+// A directory of the tests of this file, with the script `name` containing `source`.
+fn script(name: &str, source: &str) -> PathBuf {
+    let dir = env::temp_dir().join(format!("slap-run-tests-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join(name);
+    fs::write(&path, source).unwrap();
+    path
+}
+
+fn slap_run(path: &Path, args: &[&str]) -> Output {
+    slap().arg("run").arg(path).args(args).output().unwrap()
+}
+
+#[test]
+fn preloads_the_parsed_arguments() {
+    if !has_bash() {
+        return;
+    }
+    let path = script("greet-args", SCRIPT);
+    let output = slap_run(&path, &["-l", "a b", "--", "-l"]);
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        format!("{}\n-l a b -- -l\n1\na b\n-l\n", path.display())
+    );
+}
+
+#[test]
+fn exits_on_help_and_errors() {
+    if !has_bash() {
+        return;
+    }
+    let path = script("greet-help", SCRIPT);
+
+    let output = slap_run(&path, &["--version"]);
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "greet 1.0\n");
+
+    let output = slap_run(&path, &["-x"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(output.stdout, b"");
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(
+        stderr.starts_with("error: Found argument '-x'"),
+        "{}",
+        stderr
+    );
+}
+
+#[test]
+fn keeps_the_line_numbers() {
+    if !has_bash() {
+        return;
+    }
+    let source = SCRIPT.replace("printf", "echo $LINENO\nprintf");
+    let path = script("greet-lines", &source);
+    let output = slap_run(&path, &[]);
+    assert!(output.status.success(), "{:?}", output);
+    let line = source.lines().position(|x| x == "echo $LINENO").unwrap() + 1;
+    assert!(
+        String::from_utf8(output.stdout)
+            .unwrap()
+            .starts_with(&format!("{}\n", line)),
+        "line {}",
+        line
+    );
+}
+
+// Linux rejects arguments longer than 128 KiB, the script isn't one.
+#[test]
+fn runs_long_scripts() {
+    if !has_bash() {
+        return;
+    }
+    let source = format!(
+        "{}{}echo done\n",
+        SCRIPT,
+        ": 'padding line'\n".repeat(20_000)
+    );
+    let path = script("greet-long", &source);
+    let output = slap_run(&path, &["x"]);
+    assert!(output.status.success(), "{:?}", output.status);
+    assert!(String::from_utf8(output.stdout)
+        .unwrap()
+        .ends_with("\nx\ndone\n"));
+}
+
+#[cfg(unix)]
+#[test]
+fn runs_as_interpreter_link() {
+    if !has_bash() {
+        return;
+    }
+    let path = script("greet-link", SCRIPT);
+    let link = path.with_file_name("slap-run");
+    let _ = fs::remove_file(&link);
+    std::os::unix::fs::symlink(env!("CARGO_BIN_EXE_slap"), &link).unwrap();
+    let output = Command::new(&link)
+        .arg(&path)
+        .arg("x")
+        .env("XDG_CACHE_HOME", cache_home())
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}", output);
+    assert!(String::from_utf8(output.stdout)
+        .unwrap()
+        .ends_with("\nx\n0\nx\n"));
+}
+
+#[test]
+fn invalid_front_matters() {
+    let cases = [
+        ("echo hi\n", "must start with its YAML config"),
+        ("---\nname: x\n", "must be followed by a `---` line"),
+        ("---\nname: x\n---\n", "must contain an entry named 'shell'"),
+        ("---\nname: x\nshell: nope\n---\n", "Shell must be one of"),
+        (
+            "---\nname: x\nshell: elvish\n---\n",
+            "can only run bash, fish, ksh and zsh scripts",
+        ),
+    ];
+    for (i, (source, error)) in cases.iter().enumerate() {
+        let output = slap_run(&script(&format!("invalid{}", i), source), &[]);
+        assert_eq!(output.status.code(), Some(1));
+        let stderr = String::from_utf8(output.stderr).unwrap();
+        assert!(stderr.contains(error), "{}", stderr);
+    }
+}
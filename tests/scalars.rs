@@ -0,0 +1,51 @@
+//! Tests for the values of the single-value arguments emitted as scalars by `--scalars`.
+//!
+//! The generated code is evaluated by the real shells, see `common` for the ones that must be
+//! installed.
+
+#[macro_use]
+mod common;
+
+use {common::*, slap::Shell, std::ffi::OsString};
+
+target_tests! {
+    scalar_values,
+}
+
+fn scalar_values(target: &Target) {
+    let config = "name: scalars\n\
+                  args:\n  \
+                    - flag:\n      \
+                        short: f\n  \
+                    - absent:\n      \
+                        short: a\n  \
+                    - opt:\n      \
+                        long: opt\n      \
+                        takes_value: true\n";
+    let options = slap::ParseOptions {
+        scalars: true,
+        ..Default::default()
+    };
+    let args = ["-f", "--opt", "it's $x"]
+        .iter()
+        .map(OsString::from)
+        .collect::<Vec<_>>();
+    let code = slap::parse_with(config, &target.shell, &args, Some("_"), &options).unwrap();
+    let code = format!(
+        "{}\n{}\n{}\n{}\n",
+        code,
+        target.print_scalar("_flag_vals"),
+        target.print_scalar("_absent_vals"),
+        target.print_scalar("_opt_vals"),
+    );
+    let expected = match target.shell {
+        Shell::Elvish | Shell::PowerShell | Shell::Xonsh => "True\x1eFalse\x1eit's $x\x1e",
+        _ => "true\x1efalse\x1eit's $x\x1e",
+    };
+    assert_eq!(
+        String::from_utf8(target.run(&code)).unwrap(),
+        expected,
+        "generated code:\n{}",
+        code
+    );
+}
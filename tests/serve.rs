@@ -0,0 +1,127 @@
+//! Tests for the protocol of `slap serve`.
+
+mod common;
+
+use {
+    common::slap,
+    std::{
+        io::Write,
+        process::{Output, Stdio},
+    },
+};
+
+const CONFIG: &str = "name: served\n\
+                      version: \"1.0\"\n\
+                      args:\n  \
+                        - n:\n      \
+                            long: n\n      \
+                            takes_value: true\n";
+
+fn request(config: &str, args: &[&str]) -> Vec<u8> {
+    let mut request = Vec::new();
+    for field in [&args.len().to_string(), config].iter().chain(args) {
+        request.extend_from_slice(field.as_bytes());
+        request.push(0);
+    }
+    request
+}
+
+// Sends `input` to a new server and returns its output once it exits.
+fn serve(input: &[u8]) -> Output {
+    let mut child = slap()
+        .arg("serve")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(input).unwrap();
+    child.wait_with_output().unwrap()
+}
+
+// The fields of the responses of a server that answered every request.
+fn responses(requests: &[Vec<u8>]) -> Vec<String> {
+    let output = serve(&requests.concat());
+    assert!(output.status.success(), "{:?}", output);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    stdout.split_terminator('\0').map(String::from).collect()
+}
+
+#[test]
+fn answers_every_request() {
+    let fields = responses(&[
+        request(CONFIG, &["bash", "_", "--", "--n", "a b"]),
+        request(CONFIG, &["bash", "--", "--x"]),
+    ]);
+    assert_eq!(fields.len(), 6, "{:?}", fields);
+    assert_eq!(fields[0], "0");
+    assert!(fields[1].contains("_n_vals=('a b')"), "{}", fields[1]);
+    assert_eq!(fields[2], "");
+    assert_eq!(fields[3], "1");
+    assert_eq!(fields[4], "");
+    assert!(fields[5].contains("'--x'"), "{}", fields[5]);
+}
+
+#[test]
+fn answers_help_and_errors() {
+    let fields = responses(&[
+        request(CONFIG, &["bash", "--", "--help"]),
+        request(CONFIG, &["bash", "--", "-V"]),
+        request("name: [", &["bash"]),
+        request(CONFIG, &["nope"]),
+        // The server keeps answering after the errors.
+        request(CONFIG, &["bash", "_"]),
+    ]);
+    assert_eq!(fields.len(), 15, "{:?}", fields);
+    assert_eq!(&fields[..2], ["0", ""]);
+    assert!(
+        fields[2].starts_with("served 1.0\n\nUSAGE:"),
+        "{}",
+        fields[2]
+    );
+    assert_eq!(&fields[3..6], ["0", "", "served 1.0"]);
+    assert_eq!(&fields[6..8], ["1", ""]);
+    assert!(fields[8].starts_with("Error: "), "{}", fields[8]);
+    assert_eq!(&fields[9..11], ["1", ""]);
+    assert!(fields[11].contains("'nope'"), "{}", fields[11]);
+    assert_eq!(fields[12], "0");
+}
+
+#[test]
+fn answers_more_configs_than_cached() {
+    let configs = (0..20)
+        .map(|i| CONFIG.replace("served", &format!("served{}", i)))
+        .collect::<Vec<_>>();
+    let requests = configs
+        .iter()
+        .chain(configs.iter().rev())
+        .map(|x| request(x, &["bash", "--", "-V"]))
+        .collect::<Vec<_>>();
+    let fields = responses(&requests);
+    let versions = fields.chunks(3).map(|x| x[2].as_str()).collect::<Vec<_>>();
+    let expected = (0..20)
+        .chain((0..20).rev())
+        .map(|i| format!("served{} 1.0", i))
+        .collect::<Vec<_>>();
+    assert_eq!(versions, expected);
+}
+
+#[test]
+fn rejects_malformed_requests() {
+    let cases: &[(&[u8], &str)] = &[
+        (b"x\0", "it must start with the number of arguments"),
+        (b"1\0", "missing the config"),
+        (b"2\0name: x\0bash\0", "missing arguments"),
+        (
+            b"1\0name: x\0bash",
+            "every field must be terminated by a NUL byte",
+        ),
+    ];
+    for (input, error) in cases {
+        let output = serve(input);
+        assert!(!output.status.success(), "{:?}", output);
+        assert_eq!(output.stdout, b"");
+        let stderr = String::from_utf8(output.stderr).unwrap();
+        assert!(stderr.contains(error), "{}", stderr);
+    }
+}
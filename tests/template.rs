@@ -0,0 +1,32 @@
+//! Tests for the templates loaded by `--template`.
+
+use {
+    slap::{Shell, Template},
+    std::ffi::OsString,
+};
+
+#[test]
+fn invalid_templates() {
+    let err = |s: &str| format!("{:#}", Template::load(s).err().unwrap());
+    assert!(err("string '{str}'\nlist [{items}]\n").contains("`assign`"));
+    assert!(err("string '{value}'\nlist [{items}]\nassign {name} = {value}\n").contains("{value}"));
+    assert!(err("strng '{str}'\n").contains("`strng`"));
+    assert!(err("escape ab x\n").contains("single character"));
+}
+
+#[test]
+fn template_var_prefix() {
+    let config = "name: prefixed\nargs:\n  - v:\n      long: v\n";
+    let template = "string '{str}'\nlist [{items}]\nassign {name} = {value}\nvar-prefix p_\n";
+    let shell = Shell::Template(Box::new(Template::load(template).unwrap()));
+    let args = [OsString::from("--v")];
+    let code = slap::parse(config, &shell, &args, None).unwrap();
+    assert!(code.contains("p_v_occurs = "), "{}", code);
+    // The one passed to `parse` wins.
+    let code = slap::parse(config, &shell, &args, Some("q_")).unwrap();
+    assert!(
+        code.contains("q_v_occurs = ") && !code.contains("p_"),
+        "{}",
+        code
+    );
+}
@@ -0,0 +1,140 @@
+//! Tests for the native booleans and integers of the arguments with a `type`.
+//!
+//! The generated code is evaluated by the real shells, see `common` for the ones that must be
+//! installed.
+
+#[macro_use]
+mod common;
+
+use {common::*, slap::Shell, std::ffi::OsString};
+
+target_tests! {
+    typed_values,
+    untyped_after_typed,
+}
+
+fn typed_values(target: &Target) {
+    let config = "name: typed\n\
+                  args:\n  \
+                    - port:\n      \
+                        long: port\n      \
+                        takes_value: true\n      \
+                        type: int\n  \
+                    - dry:\n      \
+                        long: dry\n      \
+                        takes_value: true\n      \
+                        type: bool\n";
+    let options = slap::ParseOptions {
+        scalars: true,
+        ..Default::default()
+    };
+    let args = ["--port", "0080", "--dry", "YES"]
+        .iter()
+        .map(OsString::from)
+        .collect::<Vec<_>>();
+    let code = slap::parse_with(config, &target.shell, &args, Some("_"), &options).unwrap();
+    let code = format!(
+        "{}\n{}\n{}\n",
+        code,
+        target.print_scalar("_port_vals"),
+        target.print_scalar("_dry_vals"),
+    );
+    let expected = match target.shell {
+        Shell::Elvish | Shell::PowerShell | Shell::Xonsh => "80\x1eTrue\x1e",
+        _ => "80\x1etrue\x1e",
+    };
+    assert_eq!(
+        String::from_utf8(target.run(&code)).unwrap(),
+        expected,
+        "generated code:\n{}",
+        code
+    );
+}
+
+#[test]
+fn invalid_typed_values() {
+    let config = "name: typed\nargs:\n  - port:\n      long: port\n      takes_value: true\n      \
+                  type: int\n";
+    let err = slap::parse(config, &Shell::Bash, &[OsString::from("--port=x")], None);
+    assert!(matches!(err, Err(slap::Error::Args(_))));
+}
+
+#[test]
+fn bash_local_untyped_after_typed() {
+    local_untyped_after_typed(&BASH);
+}
+
+#[test]
+#[ignore = "needs zsh"]
+fn zsh_local_untyped_after_typed() {
+    local_untyped_after_typed(&ZSH);
+}
+
+const PAYLOAD: &str = "a[$(echo PWNED >&2)]";
+
+// Parses integers, then strings for the same variables, with `--local` or not.
+fn typed_then_untyped(target: &Target, local: bool) -> String {
+    let config = |value_type| {
+        format!(
+            "name: typed\n\
+             args:\n  \
+               - port:\n      \
+                   long: port\n      \
+                   takes_value: true\n      \
+                   type: {0}\n  \
+               - ports:\n      \
+                   long: ports\n      \
+                   multiple: true\n      \
+                   takes_value: true\n      \
+                   type: {0}\n",
+            value_type
+        )
+    };
+    let options = slap::ParseOptions {
+        scalars: true,
+        local,
+        ..Default::default()
+    };
+    let parse = |value_type, value| {
+        let args = ["--port", value, "--ports", value]
+            .iter()
+            .map(OsString::from)
+            .collect::<Vec<_>>();
+        slap::parse_with(
+            &config(value_type),
+            &target.shell,
+            &args,
+            Some("_"),
+            &options,
+        )
+        .unwrap()
+    };
+    format!(
+        "{}\n{}\n{}\n{}\n",
+        parse("int", "1"),
+        parse("string", PAYLOAD),
+        target.print_scalar("_port_vals"),
+        target.print_values("_ports_vals"),
+    )
+}
+
+// The values of a parse aren't evaluated by the shell because of the types of a previous one.
+fn untyped_after_typed(target: &Target) {
+    let code = typed_then_untyped(target, false);
+    assert_eq!(
+        String::from_utf8(target.run(&code)).unwrap(),
+        format!("{0}\x1e{0}\x1e", PAYLOAD),
+        "generated code:\n{}",
+        code
+    );
+}
+
+fn local_untyped_after_typed(target: &Target) {
+    let code = format!("f() {{\n{}}}\nf\n", typed_then_untyped(target, true));
+    assert_eq!(
+        String::from_utf8(target.run(&code)).unwrap(),
+        format!("{0}\x1e{0}\x1e", PAYLOAD),
+        "generated code:\n{}",
+        code
+    );
+}
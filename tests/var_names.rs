@@ -0,0 +1,48 @@
+//! Tests for the collisions of the names of the generated variables.
+
+use {slap::Shell, std::ffi::OsString};
+
+#[test]
+fn colliding_names() {
+    // `a_b` and `a b` generate the same variables even though they are never parsed together.
+    let config = "name: colliding\n\
+                  subcommands:\n  \
+                    - a_b:\n      \
+                        about: flat\n  \
+                    - a:\n      \
+                        subcommands:\n        \
+                          - b:\n            \
+                              about: nested\n";
+    let err = slap::parse(config, &Shell::Bash, &[OsString::from("a_b")], Some("_"));
+    match err {
+        Err(slap::Error::Config(e)) => assert!(
+            e.to_string().contains("'a_b'") && e.to_string().contains("'a b'"),
+            "{}",
+            e
+        ),
+        x => panic!(
+            "expected a config error, got {:?}",
+            x.map_err(|e| e.to_string())
+        ),
+    }
+
+    // Rejected when the config is loaded, also by the commands that don't parse arguments.
+    assert!(matches!(
+        slap::completions(config, &Shell::Bash),
+        Err(slap::Error::Config(_))
+    ));
+
+    // The names given when parsing are checked again, the default ones don't collide here.
+    let config = config.replace("a_b:", "ab:");
+    let args = [OsString::from("ab")];
+    assert!(slap::parse(&config, &Shell::Bash, &args, Some("_")).is_ok());
+    let options = slap::ParseOptions {
+        var_names: slap::VarNames {
+            separator: "".into(),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let code = slap::parse_with(&config, &Shell::Bash, &args, Some("_"), &options);
+    assert!(matches!(code, Err(slap::Error::Config(_))));
+}
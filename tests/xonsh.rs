@@ -0,0 +1,27 @@
+//! Tests for the code generated for xonsh, whose identifiers must be valid in Python.
+
+use {slap::Shell, std::ffi::OsString};
+
+#[test]
+fn xonsh_identifiers() {
+    let config = "name: py\nargs:\n  - class:\n      long: class\n";
+    let args = [OsString::from("--class")];
+    let options = |local, occurs_suffix: &str| slap::ParseOptions {
+        local,
+        var_names: slap::VarNames {
+            occurs_suffix: occurs_suffix.into(),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let parse = |options| slap::parse_with(config, &Shell::Xonsh, &args, None, &options);
+    assert!(parse(options(false, "occurs")).is_ok());
+    match parse(options(false, "")) {
+        Err(slap::Error::Codegen(e)) => assert!(e.to_string().contains("Python keyword"), "{}", e),
+        x => panic!("expected an error, got {:?}", x.map_err(|e| e.to_string())),
+    }
+    match parse(options(true, "occurs")) {
+        Err(slap::Error::Codegen(e)) => assert!(e.to_string().contains("`--local`"), "{}", e),
+        x => panic!("expected an error, got {:?}", x.map_err(|e| e.to_string())),
+    }
+}